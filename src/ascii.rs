@@ -0,0 +1,64 @@
+use core::convert::TryInto;
+
+/// Return the length of the leading ASCII bytes in `bytes`.
+#[cfg(feature = "nightly")]
+pub(crate) fn ascii_prefix_len(bytes: &[u8]) -> usize {
+    use core::simd::cmp::SimdPartialOrd;
+    use core::simd::u8x32;
+
+    const LANES: usize = 32;
+    let mut chunks = bytes.chunks_exact(LANES);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        let non_ascii = u8x32::from_slice(chunk).simd_ge(u8x32::splat(0x80));
+        if non_ascii.any() {
+            return offset + non_ascii.to_bitmask().trailing_zeros() as usize;
+        }
+        offset += LANES;
+    }
+    offset + scalar_ascii_prefix_len(chunks.remainder())
+}
+
+/// Return the length of the leading ASCII bytes in `bytes`.
+#[cfg(not(feature = "nightly"))]
+pub(crate) fn ascii_prefix_len(bytes: &[u8]) -> usize {
+    scalar_ascii_prefix_len(bytes)
+}
+
+fn scalar_ascii_prefix_len(bytes: &[u8]) -> usize {
+    const WORD: usize = core::mem::size_of::<usize>();
+    const HIGH_BITS: usize = usize::from_ne_bytes([0x80; WORD]);
+
+    // Check a word at a time and leave the byte level search to the tail
+    let mut offset = 0;
+    for chunk in bytes.chunks_exact(WORD) {
+        let word = usize::from_ne_bytes(chunk.try_into().unwrap());
+        if word & HIGH_BITS != 0 {
+            break;
+        }
+        offset += WORD;
+    }
+    offset
+        + bytes[offset..]
+            .iter()
+            .position(|b| !b.is_ascii())
+            .unwrap_or(bytes.len() - offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_prefix_len_finds_first_non_ascii() {
+        let mut bytes = vec![b'a'; 100];
+        assert_eq!(100, ascii_prefix_len(&bytes));
+        for i in [0, 1, 31, 32, 33, 63, 64, 99] {
+            bytes[i] = 0xE3;
+            assert_eq!(i, ascii_prefix_len(&bytes));
+            assert_eq!(0, ascii_prefix_len(&bytes[i..]));
+            bytes[i] = b'a';
+        }
+        assert_eq!(0, ascii_prefix_len(&[]));
+    }
+}
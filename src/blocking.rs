@@ -0,0 +1,257 @@
+//! Decoders for synchronous code.
+use crate::decoder::Utf8Decoder;
+use crate::decoder::{check_capacity, default_capacity, ErrorPolicy, Result};
+use crate::sans_io::Utf8DecoderCore;
+use futures_io::AsyncRead;
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// Incremental UTF-8 decoder over a blocking [`std::io::Read`].
+///
+/// It is an [`Iterator`] which yields the text of each read as soon as it is
+/// decoded, and keeps a sequence split across reads until the rest arrives. It
+/// decodes with [`Utf8DecoderCore`], which drops the read an invalid sequence
+/// is found in as [`Utf8Decoder`] does, so the items and the errors of a
+/// reader are the same as those of [`Utf8Decoder`] with the same capacity.
+/// Reads interrupted by a signal are retried as
+/// [`std::io::Read::read_to_end`] does.
+#[derive(Debug)]
+pub struct SyncUtf8Decoder<R> {
+    reader: R,
+    core: Utf8DecoderCore,
+    // 'buf[start..end]' holds bytes which have been read but not fed yet
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+    eof: bool,
+}
+
+impl<R> SyncUtf8Decoder<R> {
+    /// Create a new incremental UTF-8 decoder from `reader`
+    pub fn new(reader: R) -> Self {
+        SyncUtf8Decoder::with_capacity(default_capacity(), reader)
+    }
+
+    /// Create a new incremental UTF-8 decoder from `reader` with specified capacity
+    ///
+    /// As with [`Utf8Decoder`], an incomplete sequence kept for the next read
+    /// takes up the capacity so that the items are split at the same points.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is smaller than 4 bytes, the maximum length of a
+    /// UTF-8 sequence.
+    ///
+    /// [`Utf8Decoder`]: crate::Utf8Decoder
+    pub fn with_capacity(capacity: usize, reader: R) -> Self {
+        if let Err(err) = check_capacity(capacity) {
+            panic!("{}", err);
+        }
+        Self {
+            reader,
+            core: Utf8DecoderCore::new(),
+            buf: vec![0; capacity].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            eof: false,
+        }
+    }
+
+    /// Set the policy of invalid input. See [`ErrorPolicy`].
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.core.set_error_policy(policy);
+    }
+
+    /// Consumes this decoder, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Acquires a reference to the underlying reader that this
+    /// decoder is pulling from.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader that
+    /// this decoder is pulling from.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R> Iterator for SyncUtf8Decoder<R>
+where
+    R: Read,
+{
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.start < self.end {
+                match self.core.feed(&self.buf[self.start..self.end]) {
+                    Ok((decoded, consumed)) => {
+                        self.start += consumed;
+                        if !decoded.is_empty() {
+                            return Some(Ok(decoded));
+                        }
+                    }
+                    Err(err) => {
+                        // The core drops the bytes an invalid sequence is found in
+                        self.start = self.end;
+                        return Some(Err(err));
+                    }
+                }
+                continue;
+            }
+            if self.eof {
+                return None;
+            }
+            let len = self.buf.len() - self.core.pending_bytes().len();
+            let n = match self.reader.read(&mut self.buf[..len]) {
+                Ok(n) => n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.start = 0;
+            self.end = n;
+            // The upstream is closed
+            if n == 0 {
+                self.eof = true;
+                return match self.core.finish() {
+                    Ok(remains) if remains.is_empty() => None,
+                    result => Some(result),
+                };
+            }
+        }
+    }
+}
+
+/// Iterator which blocks the current thread on an asynchronous decoder.
+///
+/// It is created by [`Utf8Decoder::into_blocking_iter`]. The decoder is polled
+/// on the calling thread, which is parked until the reader wakes it up, so no
+/// executor is required. The reader must not depend on an executor running on
+/// the same thread, such as the I/O types of a single-threaded runtime.
+#[derive(Debug)]
+pub struct BlockingIter<R> {
+    decoder: Utf8Decoder<R>,
+}
+
+impl<R> BlockingIter<R> {
+    pub(crate) fn new(decoder: Utf8Decoder<R>) -> Self {
+        Self { decoder }
+    }
+
+    /// Consumes this iterator, returning the underlying decoder.
+    pub fn into_inner(self) -> Utf8Decoder<R> {
+        self.decoder
+    }
+}
+
+impl<R> Iterator for BlockingIter<R>
+where
+    R: AsyncRead + Unpin,
+{
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match self.decoder.poll_next_chunk(&mut cx) {
+                Poll::Ready(item) => return item,
+                // A spurious unpark only leads to another poll
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DecodeError;
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::channel::mpsc;
+    use futures::io::{self, AllowStdIo};
+    use futures::{StreamExt, TryStreamExt};
+
+    #[async_std::test]
+    async fn sync_decoder_matches_async_decoder() -> Result<()> {
+        let text = "$ü€💖".repeat(4);
+        for capacity in 4..8 {
+            let decoded = SyncUtf8Decoder::with_capacity(capacity, text.as_bytes())
+                .collect::<crate::Result<Vec<_>>>()?;
+            let expected = Utf8Decoder::with_capacity(capacity, AllowStdIo::new(text.as_bytes()))
+                .try_collect::<Vec<_>>()
+                .await?;
+            assert_eq!(expected, decoded);
+        }
+
+        // The text read along with an invalid sequence is carried by the error
+        let summarize = |item: crate::Result<String>| {
+            item.map_err(|err| (err.offset(), err.as_valid_prefix().map(str::to_string)))
+        };
+        let bytes = [0x24, 0xFF, 0x24, 0x25];
+        let decoded = SyncUtf8Decoder::new(&bytes[..])
+            .map(summarize)
+            .collect::<Vec<_>>();
+        let expected = Utf8Decoder::new(AllowStdIo::new(&bytes[..]))
+            .map(summarize)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(expected, decoded);
+        assert_eq!(vec![Err((Some(1), Some("$".to_string())))], decoded);
+
+        let mut decoder = SyncUtf8Decoder::new(&[0xFF, 0x24, 0xF0, 0x9F][..]);
+        assert!(matches!(
+            decoder.next(),
+            Some(Err(DecodeError::Utf8Error { .. }))
+        ));
+        assert!(decoder.next().is_none());
+
+        let mut decoder = SyncUtf8Decoder::new(&[0x24, 0xF0, 0x9F][..]);
+        assert_eq!("$", decoder.next().unwrap()?);
+        assert!(matches!(
+            decoder.next(),
+            Some(Err(DecodeError::IncompleteUtf8Sequence { bytes: remains, .. })) if remains == [0xF0, 0x9F]
+        ));
+        assert!(decoder.next().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn blocking_iter_waits_for_reader() -> Result<()> {
+        let (tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let producer = thread::spawn(move || {
+            for chunk in [vec![0x24, 0xF0, 0x9F], vec![0x92, 0x96]] {
+                thread::sleep(std::time::Duration::from_millis(10));
+                tx.unbounded_send(Ok(chunk)).unwrap();
+            }
+        });
+        let decoded = Utf8Decoder::new(rx.into_async_read())
+            .into_blocking_iter()
+            .collect::<crate::Result<Vec<_>>>()?;
+        assert_eq!(vec!["$", "💖"], decoded);
+        producer.join().unwrap();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+//! Integration with the bodies of `http-body`.
+use bytes::{Buf, Bytes};
+use futures_core::{ready, Stream};
+use http::HeaderMap;
+use http_body::{Body, Frame};
+use pin_project_lite::pin_project;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Stream of the data frames of an [`http_body::Body`], such as the body of
+    /// a `hyper` request or response.
+    ///
+    /// It is created by [`Utf8Decoder::from_body`] along with a
+    /// [`StreamReader`]. Data frames are yielded as [`Bytes`], while trailers
+    /// are kept aside and available with [`BodyStream::trailers`] once the body
+    /// has ended. An error of the body is reported as an `io::Error` of
+    /// `ErrorKind::Other` which wraps it.
+    ///
+    /// [`Utf8Decoder::from_body`]: crate::Utf8Decoder::from_body
+    /// [`StreamReader`]: crate::StreamReader
+    #[derive(Debug)]
+    pub struct BodyStream<B> {
+        #[pin]
+        body: B,
+        trailers: Option<HeaderMap>,
+    }
+}
+
+impl<B> BodyStream<B> {
+    /// Create a new stream of the data of `body`
+    pub fn new(body: B) -> Self {
+        Self {
+            body,
+            trailers: None,
+        }
+    }
+
+    /// Returns the trailers of the body if it has sent them.
+    pub fn trailers(&self) -> Option<&HeaderMap> {
+        self.trailers.as_ref()
+    }
+
+    /// Consumes this stream, returning the underlying body.
+    pub fn into_inner(self) -> B {
+        self.body
+    }
+
+    /// Acquires a reference to the underlying body.
+    pub fn get_ref(&self) -> &B {
+        &self.body
+    }
+
+    /// Acquires a mutable reference to the underlying body.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.body
+    }
+}
+
+impl<B> Stream for BodyStream<B>
+where
+    B: Body,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            let frame = match ready!(this.body.as_mut().poll_frame(cx)) {
+                Some(Ok(frame)) => frame,
+                Some(Err(err)) => return Poll::Ready(Some(Err(io::Error::other(err)))),
+                None => return Poll::Ready(None),
+            };
+            match frame.into_data() {
+                Ok(mut data) => {
+                    let data = data.copy_to_bytes(data.remaining());
+                    return Poll::Ready(Some(Ok(data)));
+                }
+                Err(frame) => {
+                    if let Ok(trailers) = frame.into_trailers() {
+                        this.trailers
+                            .get_or_insert_with(HeaderMap::new)
+                            .extend(trailers);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// [`http_body::Body`] which sends the text yielded by a stream, such as a
+    /// [`Utf8Decoder`] or text transformed from it, as data frames.
+    ///
+    /// It allows to serve text as a streaming HTTP response. An error of the
+    /// stream is the error of the body, and empty items are not sent.
+    ///
+    /// [`Utf8Decoder`]: crate::Utf8Decoder
+    #[derive(Debug)]
+    pub struct TextBody<S> {
+        #[pin]
+        stream: S,
+    }
+}
+
+impl<S> TextBody<S> {
+    /// Create a new body which sends the text of `stream`
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+
+    /// Consumes this body, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Acquires a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+impl<S, E> Body for TextBody<S>
+where
+    S: Stream<Item = Result<String, E>>,
+{
+    type Data = Bytes;
+    type Error = E;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, E>>> {
+        let mut stream = self.project().stream;
+        loop {
+            match ready!(stream.as_mut().poll_next(cx)) {
+                Some(Ok(text)) if text.is_empty() => continue,
+                Some(result) => return Poll::Ready(Some(result.map(|s| Frame::data(s.into())))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TextBody, Utf8Decoder};
+    use anyhow::Result;
+    use bytes::Bytes;
+    use futures::prelude::*;
+    use http::HeaderMap;
+    use http_body::Frame;
+    use http_body_util::{BodyExt, StreamBody};
+    use std::convert::Infallible;
+
+    #[async_std::test]
+    async fn body_decode_frames() -> Result<()> {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", "0".parse()?);
+        let frames: Vec<Result<_, Infallible>> = vec![
+            Ok(Frame::data(Bytes::from_static(&[0x24, 0xF0]))),
+            Ok(Frame::data(Bytes::from_static(&[0x9F, 0x92, 0x96]))),
+            Ok(Frame::trailers(trailers)),
+        ];
+        let mut decoder = Utf8Decoder::from_body(StreamBody::new(stream::iter(frames)));
+
+        let mut decoded = String::new();
+        while let Some(s) = decoder.next().await {
+            decoded.push_str(&s?);
+        }
+        assert_eq!("$💖", decoded);
+        let trailers = decoder.get_ref().get_ref().trailers();
+        assert_eq!("0", trailers.unwrap()["x-checksum"]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[async_std::test]
+    async fn body_decode_response() -> Result<()> {
+        let response = reqwest::Response::from(http::Response::new("$ü€💖"));
+        let decoded = Utf8Decoder::from_response(response).try_collect::<String>();
+        assert_eq!("$ü€💖", decoded.await?);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn body_from_text() -> Result<()> {
+        let decoder = Utf8Decoder::new(&[0x24, 0xC3, 0xBC][..]);
+        let body = TextBody::new(decoder.map_ok(|s| s.to_uppercase()));
+        assert_eq!("$Ü", body.collect().await?.to_bytes());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! Decoded text annotated with the kind of boundary it ended at.
+use crate::decoder::Result;
+use futures_core::{ready, FusedStream, Stream};
+use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Kind of boundary a [`Segment`] ended at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Boundary {
+    /// The text ended at an arbitrary read boundary and more text may follow
+    Read,
+    /// The text ended with a newline
+    Newline,
+    /// The underlying stream has been closed. It marks an empty segment
+    /// yielded after the last item, see [`Boundaries`].
+    Eof,
+}
+
+impl Boundary {
+    /// Returns `true` if the boundary is a natural one (newline or end of stream).
+    pub fn is_natural(&self) -> bool {
+        !matches!(self, Boundary::Read)
+    }
+}
+
+/// Decoded text with the kind of boundary it ended at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Decoded text, which is empty for [`Boundary::Eof`]
+    pub text: String,
+    /// Kind of boundary the text ended at
+    pub boundary: Boundary,
+}
+
+pin_project! {
+    /// Stream adapter which annotates each decoded item with its [`Boundary`].
+    ///
+    /// Items ending with '\n' are marked as [`Boundary::Newline`] and others as
+    /// [`Boundary::Read`]. Because the end of the stream is only known after the
+    /// last item has been yielded, an empty segment marked as [`Boundary::Eof`] is
+    /// yielded right before the stream ends. Holding each item back until the
+    /// next one arrives would delay it, so the last item itself is marked as
+    /// [`Boundary::Read`] or [`Boundary::Newline`]. The empty segment is yielded
+    /// even for an empty stream.
+    pub struct Boundaries<S> {
+        #[pin]
+        stream: S,
+        terminated: bool,
+    }
+}
+
+impl<S> Boundaries<S> {
+    /// Create a new boundary annotating adapter from a decoded string `stream`
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            terminated: false,
+        }
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Acquires a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+impl<S> Stream for Boundaries<S>
+where
+    S: Stream<Item = Result<String>>,
+{
+    type Item = Result<Segment>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let this = self.project();
+        if *this.terminated {
+            return Poll::Ready(None);
+        }
+        let segment = match ready!(this.stream.poll_next(cx)) {
+            Some(Ok(text)) => {
+                let boundary = if text.ends_with('\n') {
+                    Boundary::Newline
+                } else {
+                    Boundary::Read
+                };
+                Segment { text, boundary }
+            }
+            Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+            None => {
+                *this.terminated = true;
+                Segment {
+                    text: String::new(),
+                    boundary: Boundary::Eof,
+                }
+            }
+        };
+        Poll::Ready(Some(Ok(segment)))
+    }
+}
+
+impl<S> FusedStream for Boundaries<S>
+where
+    S: Stream<Item = Result<String>>,
+{
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn boundaries_mark_items() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(b"ab".to_vec()), Ok(b"c\n".to_vec())];
+        let decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+        let segments = Boundaries::new(decoder)
+            .map(|s| s.map(|s| (s.text, s.boundary)))
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        assert_eq!(
+            vec![
+                ("ab".to_string(), Boundary::Read),
+                ("c\n".to_string(), Boundary::Newline),
+                ("".to_string(), Boundary::Eof),
+            ],
+            segments
+        );
+
+        let segments = Boundaries::new(stream::empty::<crate::Result<String>>())
+            .try_collect::<Vec<_>>()
+            .await?;
+        let eof = Segment {
+            text: String::new(),
+            boundary: Boundary::Eof,
+        };
+        assert_eq!(vec![eof], segments);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+//! Decoder which reads out of the buffer of an `AsyncBufRead`.
+use crate::decoder::Result;
+use crate::error::{DecodeError, ErrorPolicy};
+use crate::sans_io::{step, Completion, Pending, Step};
+use crate::validated;
+use futures_core::{ready, Stream};
+use futures_io::AsyncBufRead;
+use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Incremental UTF-8 decoder which decodes directly out of the buffer of an
+    /// [`AsyncBufRead`].
+    ///
+    /// Unlike wrapping a buffered reader with [`Utf8Decoder`], bytes are not copied
+    /// into a second buffer. Only an incomplete sequence at the end of the reader's
+    /// buffer, which is at most 3 bytes, is kept aside until the rest arrives.
+    ///
+    /// An invalid sequence is reported after the valid text before it has been
+    /// yielded, and only the invalid bytes are skipped.
+    ///
+    /// [`Utf8Decoder`]: crate::Utf8Decoder
+    pub struct Utf8BufDecoder<R> {
+        #[pin]
+        reader: R,
+        // Leading bytes of a sequence which continues in the next fill
+        pending: Pending,
+        // Number of bytes consumed from the reader so far
+        position: u64,
+    }
+}
+
+impl<R> Utf8BufDecoder<R> {
+    /// Create a new incremental UTF-8 decoder from a buffered `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            pending: Pending::default(),
+            position: 0,
+        }
+    }
+
+    /// Consumes this decoder, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Acquires a reference to the underlying reader that this
+    /// decoder is pulling from.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader that
+    /// this decoder is pulling from.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R> Stream for Utf8BufDecoder<R>
+where
+    R: AsyncBufRead,
+{
+    type Item = Result<String>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let mut this = self.project();
+        loop {
+            let available = ready!(this.reader.as_mut().poll_fill_buf(cx))?;
+            // The upstream is closed
+            if available.is_empty() {
+                if !this.pending.is_empty() {
+                    let remains = this.pending.as_bytes().to_vec();
+                    let offset = *this.position - remains.len() as u64;
+                    this.pending.clear();
+                    return Poll::Ready(Some(Err(DecodeError::incomplete_at(remains, offset))));
+                }
+                return Poll::Ready(None);
+            }
+            let mut decoded = String::new();
+            let mut start = 0;
+            if !this.pending.is_empty() {
+                // Complete the sequence left by the previous fill first. Bytes
+                // which do not continue it are left for the path below
+                let kept = this.pending.len();
+                match this.pending.complete(available) {
+                    Completion::Valid(taken) => {
+                        decoded.push_str(validated::to_str(this.pending.as_bytes()));
+                        this.pending.clear();
+                        start = taken;
+                    }
+                    // The sequence continues in the next fill
+                    Completion::Incomplete => {
+                        let n = available.len();
+                        this.reader.as_mut().consume(n);
+                        *this.position += n as u64;
+                        continue;
+                    }
+                    Completion::Invalid(taken) => {
+                        // Only the invalid bytes are skipped
+                        let offset = *this.position - kept as u64;
+                        let err = DecodeError::invalid_dropping(this.pending.as_bytes(), offset);
+                        this.pending.clear();
+                        this.reader.as_mut().consume(taken);
+                        *this.position += taken as u64;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+            }
+            let rest = &available[start..];
+            let (valid, consumed) = match step(rest, ErrorPolicy::Strict) {
+                Step::Valid(valid) => {
+                    this.pending.set(&rest[valid..]);
+                    (valid, rest.len())
+                }
+                // Yield the valid text first and report the error on the next
+                // poll
+                Step::Fail { valid, .. } if valid > 0 || !decoded.is_empty() => (valid, valid),
+                Step::Fail { .. } | Step::Replace { .. } => {
+                    let err = DecodeError::invalid_at(rest, *this.position + start as u64);
+                    let skipped = err.as_invalid_bytes().map_or(0, <[u8]>::len);
+                    this.reader.as_mut().consume(start + skipped);
+                    *this.position += (start + skipped) as u64;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            };
+            decoded.push_str(validated::to_str(&rest[..valid]));
+            this.reader.as_mut().consume(start + consumed);
+            *this.position += (start + consumed) as u64;
+            if !decoded.is_empty() {
+                return Poll::Ready(Some(Ok(decoded)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::io::{self, BufReader};
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn buf_decoder_decode_across_fills() -> Result<()> {
+        let text = "$ü€💖".repeat(4);
+        for capacity in 1..8 {
+            let reader = BufReader::with_capacity(capacity, io::Cursor::new(text.clone()));
+            let decoded = Utf8BufDecoder::new(reader).try_collect::<String>().await?;
+            assert_eq!(text, decoded);
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn buf_decoder_decode_invalid() -> Result<()> {
+        let reader = BufReader::new(&[0x24, 0xFF, 0x24, 0xF0, 0x9F][..]);
+        let mut decoder = Utf8BufDecoder::new(reader);
+
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::Utf8Error { offset: 1, .. }))
+        ));
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::IncompleteUtf8Sequence { bytes: remains, offset: 3, .. })) if remains == [0xF0, 0x9F]
+        ));
+        assert!(decoder.next().await.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn buf_decoder_invalid_across_fills() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x78, 0xE2]), Ok(b"AB".to_vec())];
+        let reader = stream::iter(chunks).into_async_read();
+        let mut decoder = Utf8BufDecoder::new(reader);
+
+        assert_eq!("x", decoder.next().await.unwrap()?);
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::Utf8Error { offset: 1, bytes, .. })) if bytes == [0xE2]
+        ));
+        assert_eq!("AB", decoder.next().await.unwrap()?);
+        assert!(decoder.next().await.is_none());
+
+        // A sequence completed across fills is decoded as usual
+        let chunks: Vec<io::Result<Vec<u8>>> =
+            vec![Ok(vec![0xF0]), Ok(vec![0x9F, 0x92]), Ok(vec![0x96, 0x24])];
+        let reader = stream::iter(chunks).into_async_read();
+        let decoded = Utf8BufDecoder::new(reader).try_collect::<String>().await?;
+        assert_eq!("💖$", decoded);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,630 @@
+use crate::decoder::{Autotune, ZeroReadPolicy, INTERRUPTED_RETRIES};
+use crate::observer::DecoderObserver;
+use crate::pool::BufferPool;
+use crate::stats::DecoderStats;
+use crate::validated;
+use crate::validation::replace_invalid;
+use crate::warning::{DecodeWarning, WarningSender};
+use bytes::{Bytes, BytesMut};
+use std::fmt;
+
+/// Size of the first read window of a buffer
+const INITIAL_WINDOW_SIZE: usize = 512;
+/// Number of consecutive full reads before the capacity is doubled
+const GROW_AFTER: usize = 4;
+/// Number of consecutive small reads before the capacity is halved
+const SHRINK_AFTER: usize = 64;
+
+/// Internal buffer of a decoder.
+///
+/// Bytes are only initialized when they are about to be handed to a reader.
+/// The read window starts small and doubles each time a read fills it, so
+/// short-lived decoders and readers producing small chunks never touch most of
+/// the capacity.
+#[derive(Debug)]
+pub(crate) struct Buffer {
+    // 'buf[..filled]' holds bytes which have been read but not taken yet and
+    // 'buf[filled..]' is initialized space for the following reads
+    buf: BytesMut,
+    capacity: usize,
+    filled: usize,
+    window: usize,
+    // Length of the region returned by the last 'unfilled_mut'
+    unfilled: usize,
+    // Length of the filled prefix lent out as '&str' which is consumed lazily
+    lent: usize,
+    // Maximum number of filled bytes ever observed
+    high_water: usize,
+    stats: DecoderStats,
+    observer: Option<Box<dyn DecoderObserver + Send + Sync>>,
+    autotune: Option<Autotune>,
+    // Number of consecutive reads which filled the buffer, or used less than a
+    // quarter of it
+    full_reads: usize,
+    small_reads: usize,
+    // Pool where the storage is drawn from and returned to on drop
+    pool: Option<BufferPool>,
+    // Called with the pending bytes if there are any on drop
+    on_drop: Option<DropHook>,
+    zero_reads: ZeroReadPolicy,
+    // Number of consecutive zero-length reads which have been retried
+    zero_retries: usize,
+    interrupted_retries: usize,
+    // Number of consecutive interrupted reads which have been retried
+    interrupts: usize,
+    would_block_as_pending: bool,
+    warnings: Option<WarningSender>,
+}
+
+type DropFn = dyn FnOnce(&[u8]) + Send + Sync;
+
+/// Callback which receives the bytes a buffer is dropped with
+pub(crate) struct DropHook(Box<DropFn>);
+
+impl DropHook {
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: FnOnce(&[u8]) + Send + Sync + 'static,
+    {
+        DropHook(Box::new(f))
+    }
+}
+
+impl fmt::Debug for DropHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DropHook")
+    }
+}
+
+impl Buffer {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Buffer::from_storage(BytesMut::with_capacity(capacity), capacity, None)
+    }
+
+    pub(crate) fn with_pool(pool: &BufferPool) -> Self {
+        let capacity = pool.buffer_size();
+        Buffer::from_storage(pool.take(), capacity, Some(pool.clone()))
+    }
+
+    fn from_storage(buf: BytesMut, capacity: usize, pool: Option<BufferPool>) -> Self {
+        Self {
+            buf,
+            capacity,
+            filled: 0,
+            window: INITIAL_WINDOW_SIZE.min(capacity),
+            unfilled: 0,
+            lent: 0,
+            high_water: 0,
+            stats: DecoderStats::default(),
+            observer: None,
+            autotune: None,
+            full_reads: 0,
+            small_reads: 0,
+            pool,
+            on_drop: None,
+            zero_reads: ZeroReadPolicy::Eof,
+            zero_retries: 0,
+            interrupted_retries: INTERRUPTED_RETRIES,
+            interrupts: 0,
+            would_block_as_pending: false,
+            warnings: None,
+        }
+    }
+
+    /// Allocate a new storage, from the pool if any
+    fn alloc(&self) -> BytesMut {
+        match &self.pool {
+            Some(pool) => pool.take(),
+            None => BytesMut::with_capacity(self.capacity),
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the capacity while keeping the filled bytes.
+    ///
+    /// The capacity is never reduced below the number of filled bytes.
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.consume_lent();
+        let capacity = capacity.max(self.filled);
+        if self.buf.capacity() > capacity {
+            // Release the excess storage
+            let mut fresh = BytesMut::with_capacity(capacity);
+            fresh.extend_from_slice(self.filled());
+            self.buf = fresh;
+        }
+        self.capacity = capacity;
+        self.window = self.window.min(capacity);
+    }
+
+    /// Drop all the filled bytes and statistics while keeping the configuration
+    pub(crate) fn clear(&mut self) {
+        self.buf.clear();
+        self.filled = 0;
+        self.unfilled = 0;
+        self.lent = 0;
+        self.high_water = 0;
+        self.stats = DecoderStats::default();
+        self.full_reads = 0;
+        self.small_reads = 0;
+        self.zero_retries = 0;
+        self.interrupts = 0;
+    }
+
+    pub(crate) fn set_drop_hook(&mut self, hook: Option<DropHook>) {
+        self.on_drop = hook;
+    }
+
+    pub(crate) fn autotune(&self) -> Option<Autotune> {
+        self.autotune
+    }
+
+    /// Enable or disable the automatic capacity tuning.
+    ///
+    /// The current capacity is clamped into the bounds when enabled.
+    pub(crate) fn set_autotune(&mut self, autotune: Option<Autotune>) {
+        self.autotune = autotune;
+        self.full_reads = 0;
+        self.small_reads = 0;
+        if let Some(Autotune { min, max }) = autotune {
+            self.set_capacity(self.capacity.max(min).min(max));
+        }
+    }
+
+    /// Record a read of `n` bytes, which `full` tells whether it filled the
+    /// buffer, and adjust the capacity if the automatic tuning is enabled.
+    pub(crate) fn tune(&mut self, n: usize, full: bool) {
+        self.zero_retries = 0;
+        self.interrupts = 0;
+        self.stats.reads += 1;
+        self.stats.bytes_read += n as u64;
+        if let Some(observer) = &mut self.observer {
+            observer.on_read(n);
+        }
+        let Some(Autotune { min, max }) = self.autotune else {
+            return;
+        };
+        if full {
+            self.full_reads += 1;
+            self.small_reads = 0;
+            if self.full_reads >= GROW_AFTER && self.capacity < max {
+                self.full_reads = 0;
+                self.set_capacity((self.capacity * 2).min(max));
+                // The reads have already proven to be large
+                self.window = self.capacity;
+            }
+        } else if n < self.capacity / 4 {
+            self.small_reads += 1;
+            self.full_reads = 0;
+            if self.small_reads >= SHRINK_AFTER && self.capacity > min {
+                self.small_reads = 0;
+                self.set_capacity((self.capacity / 2).max(min));
+            }
+        } else {
+            self.full_reads = 0;
+            self.small_reads = 0;
+        }
+    }
+
+    /// Size of the next read
+    pub(crate) fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Read as much as the capacity allows from the next read on
+    pub(crate) fn open_window(&mut self) {
+        self.window = self.capacity;
+    }
+
+    /// Double the size of the following reads
+    pub(crate) fn grow_window(&mut self) {
+        self.window = (self.window * 2).min(self.capacity);
+    }
+
+    /// Returns `true` if no more bytes can be read before taking some out
+    pub(crate) fn is_full(&self) -> bool {
+        self.filled >= self.capacity
+    }
+
+    /// Number of bytes which have been read but not taken yet
+    pub(crate) fn len(&self) -> usize {
+        self.filled - self.lent
+    }
+
+    /// Bytes which have been read and not lent or taken yet
+    pub(crate) fn pending(&self) -> &[u8] {
+        &self.buf[self.lent..self.filled]
+    }
+
+    /// Maximum number of filled bytes ever observed
+    pub(crate) fn high_water(&self) -> usize {
+        self.high_water
+    }
+
+    pub(crate) fn restore_high_water(&mut self, high_water: usize) {
+        self.high_water = self.high_water.max(high_water);
+    }
+
+    pub(crate) fn reset_high_water(&mut self) {
+        self.high_water = self.len();
+    }
+
+    pub(crate) fn stats(&self) -> DecoderStats {
+        self.stats
+    }
+
+    /// Set the observer notified of the decoder events
+    pub(crate) fn set_observer(
+        &mut self,
+        observer: Option<Box<dyn DecoderObserver + Send + Sync>>,
+    ) {
+        self.observer = observer;
+    }
+
+    /// Set the sender of the warnings of replaced or skipped sequences
+    pub(crate) fn set_warnings(&mut self, warnings: Option<WarningSender>) {
+        self.warnings = warnings;
+    }
+
+    /// Send a warning of the invalid sequence `bytes` at `offset` of the input
+    /// which has been replaced with `replacement`
+    pub(crate) fn warn(&self, offset: u64, bytes: &[u8], replacement: &[u8]) {
+        if let Some(warnings) = &self.warnings {
+            warnings.send(DecodeWarning {
+                offset,
+                bytes: bytes.to_vec(),
+                replaced: !replacement.is_empty(),
+            });
+        }
+    }
+
+    /// Record `count` invalid sequences, which `replacement` replaced unless empty
+    pub(crate) fn record_invalid(&mut self, count: usize, replacement: &[u8]) {
+        let replaced = !replacement.is_empty();
+        self.stats.invalid_sequences += count as u64;
+        if replaced {
+            self.stats.replacements += count as u64;
+        }
+        if let Some(observer) = &mut self.observer {
+            (0..count).for_each(|_| observer.on_invalid(replaced));
+        }
+    }
+
+    /// Record an item which is the first `valid` filled bytes
+    pub(crate) fn record_decoded(&mut self, valid: usize) {
+        self.stats.items += 1;
+        if let Some(observer) = &mut self.observer {
+            observer.on_chunk(validated::to_str(&self.buf[..valid]));
+        }
+    }
+
+    /// Record an item which has been decoded outside of the buffer
+    pub(crate) fn record_item(&mut self, item: &str) {
+        self.stats.items += 1;
+        if let Some(observer) = &mut self.observer {
+            observer.on_chunk(item);
+        }
+    }
+
+    pub(crate) fn set_zero_read_policy(&mut self, policy: ZeroReadPolicy) {
+        self.zero_reads = policy;
+        self.zero_retries = 0;
+    }
+
+    /// Returns `true` if a zero-length read should be retried rather than taken
+    /// for the end of the stream, counting it as a retry.
+    pub(crate) fn retry_zero_read(&mut self) -> bool {
+        match self.zero_reads {
+            ZeroReadPolicy::Retry(limit) if self.zero_retries < limit => {
+                self.zero_retries += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn set_interrupted_retries(&mut self, retries: usize) {
+        self.interrupted_retries = retries;
+        self.interrupts = 0;
+    }
+
+    /// Returns `true` if a read which failed with `err` was interrupted and
+    /// should be retried, counting it as a retry.
+    pub(crate) fn retry_interrupted(&mut self, err: &futures_io::Error) -> bool {
+        if err.kind() != futures_io::ErrorKind::Interrupted {
+            return false;
+        }
+        if self.interrupts >= self.interrupted_retries {
+            return false;
+        }
+        self.interrupts += 1;
+        true
+    }
+
+    pub(crate) fn set_would_block_as_pending(&mut self, enabled: bool) {
+        self.would_block_as_pending = enabled;
+    }
+
+    /// Returns `true` if a read which failed with `err` should be taken for
+    /// pending.
+    pub(crate) fn is_pending(&self, err: &futures_io::Error) -> bool {
+        self.would_block_as_pending && err.kind() == futures_io::ErrorKind::WouldBlock
+    }
+
+    pub(crate) fn record_eof(&mut self) {
+        if let Some(observer) = &mut self.observer {
+            observer.on_eof();
+        }
+    }
+
+    /// Offset of the first filled byte in the bytes read from the reader
+    pub(crate) fn offset(&self) -> u64 {
+        self.stats.bytes_read.saturating_sub(self.filled as u64)
+    }
+
+    /// Bytes which have been read but not taken yet
+    pub(crate) fn filled(&self) -> &[u8] {
+        &self.buf[..self.filled]
+    }
+
+    /// Initialized but unfilled region where the next read should write.
+    ///
+    /// It is empty when the buffer is full.
+    ///
+    /// Only the region past the initialized length is zero-filled, but taking
+    /// decoded bytes off the front with `split_decoded` moves the window that
+    /// far into storage which has never been initialized, so in the steady
+    /// state about as many bytes are zeroed as are decoded. `AsyncRead::poll_read` takes an initialized
+    /// `&mut [u8]`, so handing it the spare capacity would be unsound even
+    /// with the `unchecked` feature.
+    pub(crate) fn unfilled_mut(&mut self) -> &mut [u8] {
+        let mut end = (self.filled + self.window)
+            .min(self.capacity)
+            .max(self.filled);
+        let tail = self.buf.capacity();
+        if tail < end {
+            if tail >= self.filled + self.window / 2 {
+                // Keep reading into the space after the filled bytes as long as it
+                // is worth a read, so the remainder is not moved on every read.
+                end = tail;
+            } else {
+                // Reclaim the space in front of the filled bytes, or reallocate
+                // when it is still shared with a split off chunk.
+                self.buf.truncate(self.filled);
+                self.buf.reserve(end - self.filled);
+            }
+        }
+        if self.buf.len() < end {
+            self.buf.resize(end, 0);
+        }
+        self.unfilled = end - self.filled;
+        &mut self.buf[self.filled..end]
+    }
+
+    /// Mark `n` bytes of the unfilled region as filled
+    pub(crate) fn advance(&mut self, n: usize) {
+        if n == self.unfilled {
+            self.grow_window();
+        }
+        self.filled += n;
+        self.high_water = self.high_water.max(self.filled);
+        debug_assert!(self.filled <= self.buf.len());
+    }
+
+    /// Drop the filled bytes after the first `len`
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.filled = self.filled.min(len);
+    }
+
+    /// Replace the invalid sequences in the filled bytes after `start` with
+    /// `replacement` and return the length of the valid prefix.
+    ///
+    /// The filled bytes may exceed the capacity afterwards, but then the valid
+    /// prefix is not empty, as at most 3 bytes of an incomplete sequence follow it.
+    pub(crate) fn replace_invalid(&mut self, start: usize, replacement: &[u8]) -> usize {
+        // Bytes before 'start' may have been replaced already, so the offset is
+        // counted back from the end
+        let base = self
+            .stats
+            .bytes_read
+            .saturating_sub((self.filled - start) as u64);
+        let filled = &self.buf[start..self.filled];
+        let mut cleaned = Vec::with_capacity(filled.len());
+        let (consumed, invalid) = replace_invalid(filled, replacement, &mut cleaned);
+        for range in &invalid {
+            self.warn(
+                base + range.start as u64,
+                &filled[range.clone()],
+                replacement,
+            );
+        }
+        let valid = start + cleaned.len();
+        cleaned.extend_from_slice(&filled[consumed..]);
+        self.splice(start, &cleaned);
+        self.record_invalid(invalid.len(), replacement);
+        valid
+    }
+
+    /// Replace the filled bytes after `start` with `bytes`
+    pub(crate) fn splice(&mut self, start: usize, bytes: &[u8]) {
+        self.buf.truncate(start);
+        self.buf.extend_from_slice(bytes);
+        self.filled = self.buf.len();
+        self.high_water = self.high_water.max(self.filled);
+    }
+
+    /// Take the first `valid` filled bytes as String and keep the rest.
+    ///
+    /// The caller must guarantee that `filled()[..valid]` is valid UTF-8.
+    pub(crate) fn split_decoded(&mut self, valid: usize) -> String {
+        debug_assert!(valid <= self.filled);
+        let remains = self.filled - valid;
+        self.filled = remains;
+        if valid >= self.capacity / 2 {
+            // Hand the storage itself over to the String rather than copying a
+            // large validated prefix, and continue with a fresh buffer.
+            let mut fresh = self.alloc();
+            fresh.extend_from_slice(&self.buf[valid..valid + remains]);
+            self.buf.truncate(valid);
+            let decoded = Vec::from(std::mem::replace(&mut self.buf, fresh));
+            return validated::into_string(decoded);
+        }
+        // The remainder ends up at the front without being moved
+        let valid = self.buf.split_to(valid);
+        let decoded = validated::to_str(&valid);
+        decoded.to_string()
+    }
+
+    /// Lend the first `valid` filled bytes as `&str` and consume them on the
+    /// next [`Buffer::consume_lent`].
+    ///
+    /// The caller must guarantee that `filled()[..valid]` is valid UTF-8.
+    pub(crate) fn lend(&mut self, valid: usize) -> &str {
+        debug_assert!(valid <= self.filled);
+        self.lent = valid;
+        validated::to_str(&self.buf[..valid])
+    }
+
+    /// Consume the bytes previously lent by [`Buffer::lend`].
+    pub(crate) fn consume_lent(&mut self) {
+        if self.lent > 0 {
+            self.filled -= self.lent;
+            let _ = self.buf.split_to(self.lent);
+            self.lent = 0;
+        }
+    }
+
+    /// Split off the first `valid` filled bytes without copying them.
+    pub(crate) fn split_bytes(&mut self, valid: usize) -> Bytes {
+        debug_assert!(valid <= self.filled);
+        self.filled -= valid;
+        self.buf.split_to(valid).freeze()
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if let Some(DropHook(hook)) = self.on_drop.take() {
+            if !self.pending().is_empty() {
+                hook(self.pending());
+            }
+        }
+        if let Some(pool) = &self.pool {
+            pool.give(std::mem::take(&mut self.buf));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_initializes_lazily() {
+        let mut buffer = Buffer::with_capacity(8 * 1024);
+        assert_eq!(0, buffer.buf.len());
+
+        assert_eq!(INITIAL_WINDOW_SIZE, buffer.unfilled_mut().len());
+        buffer.advance(10);
+        assert_eq!(INITIAL_WINDOW_SIZE, buffer.buf.len());
+
+        // Filling the whole window doubles the next one
+        let n = buffer.unfilled_mut().len();
+        buffer.advance(n);
+        assert_eq!(INITIAL_WINDOW_SIZE * 2, buffer.unfilled_mut().len());
+    }
+
+    #[test]
+    fn buffer_unfilled_is_empty_when_full() {
+        let mut buffer = Buffer::with_capacity(4);
+        buffer.splice(0, &[0x24, 0xF0, 0x9F, 0x92, 0x96]);
+
+        assert!(buffer.is_full());
+        assert!(buffer.unfilled_mut().is_empty());
+    }
+
+    #[test]
+    fn buffer_split_decoded_keeps_remains() {
+        let mut buffer = Buffer::with_capacity(8);
+        buffer.unfilled_mut()[..3].copy_from_slice(&[0x24, 0xF0, 0x9F]);
+        buffer.advance(3);
+
+        assert_eq!("$", buffer.split_decoded(1));
+        assert_eq!(&[0xF0, 0x9F], buffer.filled());
+
+        buffer.unfilled_mut()[..2].copy_from_slice(&[0x92, 0x96]);
+        buffer.advance(2);
+        assert_eq!("💖", buffer.split_decoded(4));
+        assert!(buffer.filled().is_empty());
+    }
+
+    #[test]
+    fn buffer_reads_into_tail_without_moving_remains() {
+        let mut buffer = Buffer::with_capacity(1024);
+        buffer.unfilled_mut()[..3].copy_from_slice(&[0x24, 0xF0, 0x9F]);
+        buffer.advance(3);
+        buffer.split_decoded(1);
+
+        let remains = buffer.filled().as_ptr();
+        assert_eq!(INITIAL_WINDOW_SIZE, buffer.unfilled_mut().len());
+        assert_eq!(remains, buffer.filled().as_ptr());
+    }
+
+    #[test]
+    fn buffer_set_capacity_keeps_filled() {
+        let mut buffer = Buffer::with_capacity(1024);
+        buffer.unfilled_mut()[..3].copy_from_slice(&[0x24, 0xF0, 0x9F]);
+        buffer.advance(3);
+
+        buffer.set_capacity(4);
+        assert_eq!(4, buffer.capacity());
+        assert_eq!(&[0x24, 0xF0, 0x9F], buffer.filled());
+        assert_eq!(1, buffer.unfilled_mut().len());
+
+        buffer.set_capacity(2);
+        assert_eq!(3, buffer.capacity());
+    }
+
+    #[test]
+    fn buffer_replace_invalid() {
+        let mut buffer = Buffer::with_capacity(8);
+        buffer.unfilled_mut()[..6].copy_from_slice(&[0x24, 0xFF, 0x24, 0x80, 0xF0, 0x9F]);
+        buffer.advance(6);
+
+        assert_eq!(8, buffer.replace_invalid(1, "\u{FFFD}".as_bytes()));
+        assert_eq!("$\u{FFFD}$\u{FFFD}", buffer.lend(8));
+        buffer.consume_lent();
+        assert_eq!(&[0xF0, 0x9F], buffer.filled());
+    }
+
+    #[test]
+    fn buffer_tracks_high_water() {
+        let mut buffer = Buffer::with_capacity(8);
+        buffer.unfilled_mut();
+        buffer.advance(5);
+        buffer.split_decoded(4);
+        assert_eq!(1, buffer.len());
+        assert_eq!(5, buffer.high_water());
+
+        buffer.reset_high_water();
+        assert_eq!(1, buffer.high_water());
+    }
+
+    #[test]
+    fn buffer_autotune_within_bounds() {
+        let mut buffer = Buffer::with_capacity(16);
+        buffer.set_autotune(Some(Autotune { min: 8, max: 32 }));
+
+        for _ in 0..GROW_AFTER * 2 {
+            buffer.tune(16, true);
+        }
+        assert_eq!(32, buffer.capacity());
+
+        for _ in 0..SHRINK_AFTER * 3 {
+            buffer.tune(1, false);
+        }
+        assert_eq!(8, buffer.capacity());
+    }
+}
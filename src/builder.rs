@@ -0,0 +1,295 @@
+//! Builder of a configured decoder.
+use crate::buffer::DropHook;
+use crate::decoder::{
+    default_capacity, Autotune, ErrorPolicy, ReadStrategy, Utf8Decoder, ZeroReadPolicy, READ_BUDGET,
+};
+use crate::meter::ThroughputMeter;
+use crate::observer::DecoderObserver;
+use crate::pool::BufferPool;
+
+/// Builder of a [`Utf8Decoder`] created by [`Utf8Decoder::builder`].
+///
+/// It collects the options of a decoder before wrapping a reader with
+/// [`Utf8DecoderBuilder::build`]. Each option corresponds to a setter of
+/// [`Utf8Decoder`] and defaults to what [`Utf8Decoder::new`] uses.
+///
+/// Presets such as [`Utf8DecoderBuilder::terminal`] bundle the options for
+/// common kinds of input, and can be tuned further like any other builder.
+#[derive(Debug, Default)]
+pub struct Utf8DecoderBuilder {
+    capacity: Option<usize>,
+    pool: Option<BufferPool>,
+    strategy: ReadStrategy,
+    policy: ErrorPolicy,
+    direct: bool,
+    autotune: Option<Autotune>,
+    shrink: bool,
+    large_reads: bool,
+    meter: Option<ThroughputMeter>,
+    initial: Vec<u8>,
+    on_drop: Option<DropHook>,
+    observer: Option<Box<dyn DecoderObserver + Send + Sync>>,
+    max_total_bytes: Option<usize>,
+    max_total_chars: Option<usize>,
+    zero_reads: ZeroReadPolicy,
+    interrupted_retries: Option<usize>,
+    would_block_as_pending: bool,
+    #[cfg(feature = "timer")]
+    read_timeout: Option<std::time::Duration>,
+    #[cfg(feature = "timer")]
+    incomplete_timeout: Option<std::time::Duration>,
+}
+
+impl Utf8DecoderBuilder {
+    /// Create a new builder with the default options
+    pub fn new() -> Self {
+        Utf8DecoderBuilder::default()
+    }
+
+    /// Preset for interactive output such as a terminal.
+    ///
+    /// Text is yielded as soon as it arrives from a small buffer, and invalid
+    /// input is replaced with U+FFFD as a terminal would display it.
+    pub fn terminal() -> Self {
+        Utf8DecoderBuilder::new()
+            .capacity(4 * 1024)
+            .read_strategy(ReadStrategy::SingleRead)
+            .lossy()
+    }
+
+    /// Preset for line oriented text such as logs.
+    ///
+    /// Reads are coalesced while the reader is ready so lines are rarely split
+    /// across items, and invalid input is replaced with U+FFFD so that a single
+    /// broken line does not stop the stream.
+    pub fn log_lines() -> Self {
+        Utf8DecoderBuilder::new()
+            .capacity(16 * 1024)
+            .eager_drain(true)
+            .lossy()
+    }
+
+    /// Preset for bulk decoding of large files.
+    ///
+    /// Large reads are enabled (see [`Utf8Decoder::enable_large_reads`]) and
+    /// invalid input is reported as an error.
+    pub fn bulk_file() -> Self {
+        Utf8DecoderBuilder::new()
+            .large_reads()
+            .error_policy(ErrorPolicy::Strict)
+    }
+
+    /// Set the capacity of the internal buffer.
+    ///
+    /// It is ignored when the buffer is drawn from a [`BufferPool`].
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Draw the internal buffer from `pool`. See [`Utf8Decoder::with_pool`].
+    pub fn pool(mut self, pool: &BufferPool) -> Self {
+        self.pool = Some(pool.clone());
+        self
+    }
+
+    /// See [`Utf8Decoder::set_read_strategy`].
+    pub fn read_strategy(mut self, strategy: ReadStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// See [`Utf8Decoder::set_eager_drain`].
+    pub fn eager_drain(mut self, enabled: bool) -> Self {
+        self.strategy = if enabled {
+            ReadStrategy::Budget(READ_BUDGET)
+        } else {
+            ReadStrategy::SingleRead
+        };
+        self
+    }
+
+    /// See [`Utf8Decoder::set_error_policy`].
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Replace invalid input with U+FFFD. A shorthand of [`ErrorPolicy::Replace`].
+    pub fn lossy(self) -> Self {
+        self.error_policy(ErrorPolicy::Replace)
+    }
+
+    /// See [`Utf8Decoder::set_direct_read`].
+    pub fn direct_read(mut self, enabled: bool) -> Self {
+        self.direct = enabled;
+        self
+    }
+
+    /// See [`Utf8Decoder::set_autotune`].
+    pub fn autotune(mut self, autotune: Autotune) -> Self {
+        self.autotune = Some(autotune);
+        self
+    }
+
+    /// See [`Utf8Decoder::set_shrink_to_fit`].
+    pub fn shrink_to_fit(mut self, enabled: bool) -> Self {
+        self.shrink = enabled;
+        self
+    }
+
+    /// See [`Utf8Decoder::enable_large_reads`].
+    pub fn large_reads(mut self) -> Self {
+        self.large_reads = true;
+        self
+    }
+
+    /// See [`Utf8Decoder::set_throughput_meter`].
+    pub fn throughput_meter(mut self, meter: ThroughputMeter) -> Self {
+        self.meter = Some(meter);
+        self
+    }
+
+    /// See [`Utf8Decoder::with_initial_bytes`].
+    pub fn initial_bytes(mut self, bytes: &[u8]) -> Self {
+        self.initial = bytes.to_vec();
+        self
+    }
+
+    /// See [`Utf8Decoder::on_drop_remainder`].
+    pub fn on_drop_remainder<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&[u8]) + Send + Sync + 'static,
+    {
+        self.on_drop = Some(DropHook::new(f));
+        self
+    }
+
+    /// See [`Utf8Decoder::set_observer`].
+    pub fn observer<O>(mut self, observer: O) -> Self
+    where
+        O: DecoderObserver + Send + Sync + 'static,
+    {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// See [`Utf8Decoder::set_zero_read_policy`].
+    pub fn zero_read_policy(mut self, policy: ZeroReadPolicy) -> Self {
+        self.zero_reads = policy;
+        self
+    }
+
+    /// See [`Utf8Decoder::set_interrupted_retries`].
+    pub fn interrupted_retries(mut self, retries: usize) -> Self {
+        self.interrupted_retries = Some(retries);
+        self
+    }
+
+    /// See [`Utf8Decoder::set_would_block_as_pending`].
+    pub fn would_block_as_pending(mut self, enabled: bool) -> Self {
+        self.would_block_as_pending = enabled;
+        self
+    }
+
+    /// See [`Utf8Decoder::set_max_total_bytes`].
+    pub fn max_total_bytes(mut self, max: usize) -> Self {
+        self.max_total_bytes = Some(max);
+        self
+    }
+
+    /// See [`Utf8Decoder::set_max_total_chars`].
+    pub fn max_total_chars(mut self, max: usize) -> Self {
+        self.max_total_chars = Some(max);
+        self
+    }
+
+    /// See [`Utf8Decoder::set_read_timeout`].
+    #[cfg(feature = "timer")]
+    pub fn read_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// See [`Utf8Decoder::set_incomplete_timeout`].
+    #[cfg(feature = "timer")]
+    pub fn incomplete_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.incomplete_timeout = Some(timeout);
+        self
+    }
+
+    /// Create a decoder of `reader` with the options.
+    pub fn build<R>(self, reader: R) -> Utf8Decoder<R> {
+        let mut decoder = match &self.pool {
+            Some(pool) => Utf8Decoder::with_pool(pool, reader),
+            None => {
+                let capacity = self.capacity.unwrap_or_else(default_capacity);
+                Utf8Decoder::with_capacity(capacity, reader)
+            }
+        };
+        decoder.set_read_strategy(self.strategy);
+        decoder.set_error_policy(self.policy);
+        decoder.set_direct_read(self.direct);
+        decoder.set_autotune(self.autotune);
+        decoder.set_shrink_to_fit(self.shrink);
+        if self.large_reads {
+            decoder.enable_large_reads();
+        }
+        decoder.set_throughput_meter(self.meter);
+        decoder.set_initial_bytes(&self.initial);
+        decoder.set_drop_hook(self.on_drop);
+        decoder.set_observer(self.observer);
+        decoder.set_zero_read_policy(self.zero_reads);
+        if let Some(retries) = self.interrupted_retries {
+            decoder.set_interrupted_retries(retries);
+        }
+        decoder.set_would_block_as_pending(self.would_block_as_pending);
+        decoder.set_max_total_bytes(self.max_total_bytes);
+        decoder.set_max_total_chars(self.max_total_chars);
+        #[cfg(feature = "timer")]
+        {
+            decoder.set_read_timeout(self.read_timeout);
+            decoder.set_incomplete_timeout(self.incomplete_timeout);
+        }
+        decoder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn builder_build_decoder() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24, 0xFF]), Ok(vec![0x24])];
+        let decoder = Utf8Decoder::builder()
+            .capacity(16)
+            .read_strategy(ReadStrategy::UntilPending)
+            .lossy()
+            .build(stream::iter(chunks).into_async_read());
+        assert_eq!(16, decoder.capacity());
+
+        let decoded = decoder.try_collect::<Vec<_>>().await?;
+        assert_eq!(vec!["$\u{FFFD}$"], decoded);
+
+        Ok(())
+    }
+
+    #[test]
+    fn builder_presets() {
+        let builder = Utf8DecoderBuilder::terminal();
+        assert_eq!(ErrorPolicy::Replace, builder.policy);
+
+        let builder = Utf8DecoderBuilder::log_lines();
+        assert_eq!(ReadStrategy::Budget(READ_BUDGET), builder.strategy);
+
+        // Large reads take precedence over a small capacity
+        let decoder = Utf8DecoderBuilder::bulk_file()
+            .capacity(4)
+            .build(io::empty());
+        assert!(decoder.capacity() >= 64 * 1024);
+    }
+}
@@ -0,0 +1,73 @@
+//! Reader which reads several readers back-to-back.
+use futures_core::ready;
+use futures_io::AsyncRead;
+use pin_project_lite::pin_project;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Reader which reads `first` until it is closed and then `second`.
+    ///
+    /// It is created by [`Utf8Decoder::chain`], which decodes the readers as a
+    /// single input so that a sequence split at the end of `first` is completed
+    /// by the start of `second`.
+    ///
+    /// [`Utf8Decoder::chain`]: crate::Utf8Decoder::chain
+    #[derive(Debug)]
+    pub struct ChainReader<A, B> {
+        #[pin]
+        first: A,
+        #[pin]
+        second: B,
+        done_first: bool,
+    }
+}
+
+impl<A, B> ChainReader<A, B> {
+    /// Create a new reader which reads `first` and then `second`
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+            done_first: false,
+        }
+    }
+
+    /// Consumes this reader, returning the underlying readers.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+
+    /// Acquires references to the underlying readers.
+    pub fn get_ref(&self) -> (&A, &B) {
+        (&self.first, &self.second)
+    }
+
+    /// Acquires mutable references to the underlying readers.
+    pub fn get_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.first, &mut self.second)
+    }
+}
+
+impl<A, B> AsyncRead for ChainReader<A, B>
+where
+    A: AsyncRead,
+    B: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        if !*this.done_first {
+            match ready!(this.first.poll_read(cx, buf))? {
+                // An empty buffer does not tell that the reader is closed
+                0 if !buf.is_empty() => *this.done_first = true,
+                n => return Poll::Ready(Ok(n)),
+            }
+        }
+        this.second.poll_read(cx, buf)
+    }
+}
@@ -0,0 +1,125 @@
+//! Stream of byte chunks received from a channel.
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Stream of the byte chunks received from the receiver of a channel,
+    /// which ends once every sender has been dropped.
+    ///
+    /// It is created by [`Utf8Decoder::from_receiver`] along with a
+    /// [`StreamReader`] so that bytes sent over a channel are decoded without
+    /// any glue code. The receivers below are supported:
+    ///
+    /// - `tokio::sync::mpsc::Receiver<Vec<u8>>` and
+    ///   `tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>` with the `tokio` feature
+    /// - `async_channel::Receiver<Vec<u8>>` with the `async_channel` feature
+    ///
+    /// [`Utf8Decoder::from_receiver`]: crate::Utf8Decoder::from_receiver
+    /// [`StreamReader`]: crate::StreamReader
+    #[derive(Debug)]
+    pub struct ReceiverStream<C> {
+        #[pin]
+        rx: C,
+    }
+}
+
+impl<C> ReceiverStream<C> {
+    /// Create a new stream of the chunks received by `rx`
+    pub fn new(rx: C) -> Self {
+        Self { rx }
+    }
+
+    /// Consumes this stream, returning the underlying receiver.
+    pub fn into_inner(self) -> C {
+        self.rx
+    }
+
+    /// Acquires a reference to the underlying receiver.
+    pub fn get_ref(&self) -> &C {
+        &self.rx
+    }
+
+    /// Acquires a mutable reference to the underlying receiver.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.rx
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Stream for ReceiverStream<tokio::sync::mpsc::Receiver<Vec<u8>>> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let chunk = ready!(self.project().rx.poll_recv(cx));
+        Poll::Ready(chunk.map(Ok))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Stream for ReceiverStream<tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let chunk = ready!(self.project().rx.poll_recv(cx));
+        Poll::Ready(chunk.map(Ok))
+    }
+}
+
+#[cfg(feature = "async_channel")]
+impl Stream for ReceiverStream<async_channel::Receiver<Vec<u8>>> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let chunk = ready!(self.project().rx.poll_next(cx));
+        Poll::Ready(chunk.map(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::prelude::*;
+
+    #[cfg(feature = "tokio")]
+    #[async_std::test]
+    async fn receiver_tokio() -> Result<()> {
+        use tokio::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel(4);
+        let mut decoder = Utf8Decoder::from_receiver(rx);
+        tx.send(vec![0x24, 0xF0]).await?;
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        tx.send(vec![0x9F, 0x92, 0x96]).await?;
+        drop(tx);
+        assert_eq!("💖", decoder.next().await.unwrap()?);
+        assert!(decoder.next().await.is_none());
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send(vec![0x24])?;
+        drop(tx);
+        let decoded = Utf8Decoder::from_receiver(rx).try_collect::<String>();
+        assert_eq!("$", decoded.await?);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "async_channel")]
+    #[async_std::test]
+    async fn receiver_async_channel() -> Result<()> {
+        let (tx, rx) = async_channel::bounded(4);
+        // The receiver of async-channel is not Unpin
+        let mut decoder = std::pin::pin!(Utf8Decoder::from_receiver(rx));
+        tx.send(vec![0x24, 0xF0]).await?;
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        tx.send(vec![0x9F, 0x92, 0x96]).await?;
+        drop(tx);
+        assert_eq!("💖", decoder.next().await.unwrap()?);
+        assert!(decoder.next().await.is_none());
+
+        Ok(())
+    }
+}
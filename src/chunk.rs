@@ -0,0 +1,156 @@
+//! Zero-copy decoded chunks backed by a shared buffer.
+use crate::decoder::{Result, Utf8Decoder};
+use crate::validated;
+use bytes::Bytes;
+use futures_core::{ready, Stream};
+use futures_io::AsyncRead;
+use pin_project_lite::pin_project;
+use std::fmt;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Decoded text which shares the internal buffer of the decoder.
+///
+/// It derefs to `str` and is cheap to clone. The buffer region it refers to is
+/// not reused by the decoder until all chunks referring to it have been dropped.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Utf8Chunk(Bytes);
+
+impl Utf8Chunk {
+    /// Extracts a string slice containing the entire chunk.
+    pub fn as_str(&self) -> &str {
+        // The decoder only creates chunks from validated bytes
+        validated::to_str(&self.0)
+    }
+
+    /// Consumes this chunk, returning the underlying bytes.
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+impl Deref for Utf8Chunk {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Utf8Chunk {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<[u8]> for Utf8Chunk {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Utf8Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Utf8Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl PartialEq<str> for Utf8Chunk {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Utf8Chunk {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl From<Utf8Chunk> for String {
+    fn from(chunk: Utf8Chunk) -> Self {
+        chunk.as_str().to_string()
+    }
+}
+
+pin_project! {
+    /// Stream of zero-copy [`Utf8Chunk`] items created by [`Utf8Decoder::into_chunks`].
+    pub struct Utf8Chunks<R> {
+        #[pin]
+        decoder: Utf8Decoder<R>,
+    }
+}
+
+impl<R> Utf8Chunks<R> {
+    pub(crate) fn new(decoder: Utf8Decoder<R>) -> Self {
+        Self { decoder }
+    }
+
+    /// Consumes this stream, returning the underlying decoder.
+    pub fn into_inner(self) -> Utf8Decoder<R> {
+        self.decoder
+    }
+
+    /// Acquires a reference to the underlying decoder.
+    pub fn get_ref(&self) -> &Utf8Decoder<R> {
+        &self.decoder
+    }
+
+    /// Acquires a mutable reference to the underlying decoder.
+    pub fn get_mut(&mut self) -> &mut Utf8Decoder<R> {
+        &mut self.decoder
+    }
+}
+
+impl<R> Stream for Utf8Chunks<R>
+where
+    R: AsyncRead,
+{
+    type Item = Result<Utf8Chunk>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let mut decoder = self.project().decoder;
+        let result = ready!(decoder.as_mut().poll_decode(cx));
+        let buf = decoder.buffer_mut();
+        Poll::Ready(result.map(|r| r.map(|valid| Utf8Chunk(buf.split_bytes(valid)))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::channel::mpsc;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn chunks_decode_incremental() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut chunks = Utf8Decoder::with_capacity(16, rx.into_async_read()).into_chunks();
+
+        tx.send(Ok(vec![0x24, 0xF0, 0x9F])).await?;
+        let first = chunks.next().await.unwrap()?;
+        tx.send(Ok(vec![0x92, 0x96, 0x24, 0x24, 0x24, 0x24, 0x24]))
+            .await?;
+        let second = chunks.next().await.unwrap()?;
+        drop(tx);
+
+        // Both chunks stay valid while the buffer is reused
+        assert_eq!("$", first.as_str());
+        assert_eq!("💖$$$$$", second.as_str());
+        assert!(chunks.next().await.is_none());
+
+        Ok(())
+    }
+}
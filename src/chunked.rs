@@ -0,0 +1,263 @@
+//! HTTP/1.1 chunked transfer-encoding stage placed in front of the decoder.
+use futures_core::ready;
+use futures_io::{AsyncRead, Error, ErrorKind};
+use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const BUF_SIZE: usize = 1024;
+const MAX_SIZE_DIGITS: usize = 16;
+const MAX_TRAILERS_LEN: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Size { size: u64, digits: usize },
+    Extension { size: u64 },
+    SizeLf { size: u64 },
+    Data(u64),
+    DataCr,
+    DataLf,
+    Trailer,
+    Done,
+}
+
+pin_project! {
+    /// Reader which decodes HTTP/1.1 chunked transfer-encoding from `reader`.
+    ///
+    /// Chunk sizes, chunk extensions, and CRLFs may be split across reads at any
+    /// byte. Chunk extensions are ignored and trailer fields are collected and
+    /// available from [`ChunkedReader::trailers`] once the last chunk has been read.
+    /// Wrap it with [`Utf8Decoder`](crate::Utf8Decoder) to decode the body text.
+    pub struct ChunkedReader<R> {
+        #[pin]
+        reader: R,
+        buf: Box<[u8]>,
+        pos: usize,
+        end: usize,
+        state: State,
+        line: Vec<u8>,
+        trailers: Vec<String>,
+    }
+}
+
+impl<R> ChunkedReader<R> {
+    /// Create a new chunked transfer-encoding reader from `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: vec![0; BUF_SIZE].into_boxed_slice(),
+            pos: 0,
+            end: 0,
+            state: State::Size { size: 0, digits: 0 },
+            line: Vec::new(),
+            trailers: Vec::new(),
+        }
+    }
+
+    /// Returns `true` once the last chunk and the trailer section have been read.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Trailer field lines received after the last chunk.
+    pub fn trailers(&self) -> &[String] {
+        &self.trailers
+    }
+
+    /// Consumes this reader, returning the underlying reader and the bytes which
+    /// have been read from it but not consumed by the chunked body.
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        let rest = self.buf[self.pos..self.end].to_vec();
+        (self.reader, rest)
+    }
+
+    /// Acquires a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+fn invalid(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn end_of_size_line(size: u64) -> State {
+    if size == 0 {
+        State::Trailer
+    } else {
+        State::Data(size)
+    }
+}
+
+fn advance(
+    state: &mut State,
+    line: &mut Vec<u8>,
+    trailers: &mut Vec<String>,
+    b: u8,
+) -> Result<(), Error> {
+    *state = match (*state, b) {
+        (State::Size { size, digits }, b) if b.is_ascii_hexdigit() => {
+            if digits == MAX_SIZE_DIGITS {
+                return Err(invalid("chunk size is too large"));
+            }
+            let digit = (b as char).to_digit(16).unwrap() as u64;
+            State::Size {
+                size: size << 4 | digit,
+                digits: digits + 1,
+            }
+        }
+        (State::Size { digits: 0, .. }, _) => return Err(invalid("invalid chunk size")),
+        (State::Size { size, .. }, b';' | b' ' | b'\t') => State::Extension { size },
+        (State::Size { size, .. }, b'\r') => State::SizeLf { size },
+        (State::Size { size, .. }, b'\n') => end_of_size_line(size),
+        (State::Size { .. }, _) => return Err(invalid("invalid chunk size")),
+        (State::Extension { size }, b'\n') => end_of_size_line(size),
+        (State::Extension { size }, _) => State::Extension { size },
+        (State::SizeLf { size }, b'\n') => end_of_size_line(size),
+        (State::DataCr, b'\r') => State::DataLf,
+        (State::DataCr | State::DataLf, b'\n') => State::Size { size: 0, digits: 0 },
+        (State::SizeLf { .. } | State::DataCr | State::DataLf, _) => {
+            return Err(invalid("missing CRLF in chunked body"))
+        }
+        (State::Trailer, b'\n') => {
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if line.is_empty() {
+                State::Done
+            } else {
+                trailers.push(String::from_utf8_lossy(line).into_owned());
+                line.clear();
+                State::Trailer
+            }
+        }
+        (State::Trailer, b) => {
+            let total = trailers.iter().map(|t| t.len()).sum::<usize>() + line.len();
+            if total >= MAX_TRAILERS_LEN {
+                return Err(invalid("trailer section is too large"));
+            }
+            line.push(b);
+            State::Trailer
+        }
+        (State::Data(_) | State::Done, _) => unreachable!(),
+    };
+    Ok(())
+}
+
+impl<R> AsyncRead for ChunkedReader<R>
+where
+    R: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut [u8],
+    ) -> Poll<Result<usize, Error>> {
+        let mut this = self.project();
+        if out.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        loop {
+            match *this.state {
+                State::Done => return Poll::Ready(Ok(0)),
+                State::Data(remaining) => {
+                    let max = remaining.min(out.len() as u64) as usize;
+                    let n = if this.pos < this.end {
+                        // Serve the data which has been read together with a control line
+                        let n = max.min(*this.end - *this.pos);
+                        out[..n].copy_from_slice(&this.buf[*this.pos..*this.pos + n]);
+                        *this.pos += n;
+                        n
+                    } else {
+                        ready!(this.reader.as_mut().poll_read(cx, &mut out[..max]))?
+                    };
+                    if n == 0 {
+                        return Poll::Ready(Err(ErrorKind::UnexpectedEof.into()));
+                    }
+                    *this.state = match remaining - n as u64 {
+                        0 => State::DataCr,
+                        remaining => State::Data(remaining),
+                    };
+                    return Poll::Ready(Ok(n));
+                }
+                _ => {
+                    if *this.pos == *this.end {
+                        let n = ready!(this.reader.as_mut().poll_read(cx, this.buf))?;
+                        if n == 0 {
+                            return Poll::Ready(Err(ErrorKind::UnexpectedEof.into()));
+                        }
+                        *this.pos = 0;
+                        *this.end = n;
+                    }
+                    while *this.pos < *this.end
+                        && !matches!(*this.state, State::Data(_) | State::Done)
+                    {
+                        let b = this.buf[*this.pos];
+                        *this.pos += 1;
+                        advance(this.state, this.line, this.trailers, b)?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    fn byte_by_byte(input: &[u8]) -> impl AsyncRead {
+        let chunks = input
+            .iter()
+            .map(|b| Ok(vec![*b]))
+            .collect::<Vec<io::Result<Vec<u8>>>>();
+        stream::iter(chunks).into_async_read()
+    }
+
+    #[async_std::test]
+    async fn chunked_reader_byte_by_byte() -> Result<()> {
+        let input = b"4;ext=1\r\nab\xE2\x9C\r\nC \r\n\x96 0123456789\r\n0\r\nX-Sum: 1\r\n\r\nNEXT";
+        let mut reader = ChunkedReader::new(byte_by_byte(input));
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).await?;
+
+        assert_eq!("ab✖ 0123456789".as_bytes(), &body[..]);
+        assert_eq!(&["X-Sum: 1".to_string()], reader.trailers());
+        assert!(reader.is_done());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn chunked_reader_decode() -> Result<()> {
+        let input = &b"3\r\n\xF0\x9F\x92\r\n1\r\n\x96\r\n0\r\n\r\n"[..];
+        let mut decoder = Utf8Decoder::new(ChunkedReader::new(input));
+
+        assert_eq!("💖", decoder.next().await.unwrap()?);
+        assert!(decoder.next().await.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn chunked_reader_invalid() -> Result<()> {
+        let mut reader = ChunkedReader::new(&b"zz\r\n"[..]);
+        let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+
+        let mut reader = ChunkedReader::new(&b"4\r\nab"[..]);
+        let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+
+        Ok(())
+    }
+}
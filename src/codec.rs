@@ -0,0 +1,190 @@
+//! Codec which frames bytes into decoded text.
+use crate::error::{DecodeError, ErrorPolicy};
+use crate::sans_io::Utf8DecoderCore;
+use bytes::{Buf, BytesMut};
+
+/// Codec which decodes bytes into text for framed readers and encodes text
+/// for framed writers.
+///
+/// It implements the `Decoder` and `Encoder` traits of `tokio-util` with the
+/// `tokio_util` feature, and of `asynchronous-codec` with the
+/// `asynchronous_codec` feature.
+///
+/// Each frame is the text decoded from the bytes read so far, so a frame never
+/// ends in the middle of a character. A sequence split across reads is left in
+/// the buffer until the rest arrives, and reported at the end of the input
+/// with [`ErrorPolicy::Strict`]. Invalid input is dealt with in the same way
+/// as [`Utf8DecoderCore`] does.
+#[derive(Debug, Default, Clone)]
+pub struct Utf8Codec {
+    core: Utf8DecoderCore,
+}
+
+impl Utf8Codec {
+    /// Create a new codec which reports invalid input as errors
+    pub fn new() -> Self {
+        Utf8Codec::default()
+    }
+
+    /// Create a new codec which deals with invalid input according to `policy`
+    pub fn with_error_policy(policy: ErrorPolicy) -> Self {
+        Self {
+            core: Utf8DecoderCore::with_error_policy(policy),
+        }
+    }
+
+    /// Set the policy of invalid input. See [`ErrorPolicy`].
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.core.set_error_policy(policy);
+    }
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<String>, DecodeError> {
+        self.feed(src, false)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<String>, DecodeError> {
+        if let Some(decoded) = self.feed(src, true)? {
+            return Ok(Some(decoded));
+        }
+        let decoded = self.core.finish()?;
+        Ok(Some(decoded).filter(|s| !s.is_empty()))
+    }
+
+    fn feed(&mut self, src: &mut BytesMut, eof: bool) -> Result<Option<String>, DecodeError> {
+        if src.is_empty() {
+            return Ok(None);
+        }
+        match self.core.feed(src) {
+            Ok((decoded, mut consumed)) => {
+                if !eof {
+                    // Leave an incomplete sequence in the buffer so that framed
+                    // readers see the bytes remaining at the end of the input
+                    consumed -= self.core.unkeep();
+                }
+                src.advance(consumed);
+                Ok(Some(decoded).filter(|s| !s.is_empty()))
+            }
+            Err(err) => {
+                // The bytes are dropped along with the error
+                src.clear();
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio_util")]
+impl tokio_util::codec::Decoder for Utf8Codec {
+    type Item = String;
+    type Error = DecodeError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<String>, DecodeError> {
+        Utf8Codec::decode(self, src)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<String>, DecodeError> {
+        Utf8Codec::decode_eof(self, src)
+    }
+}
+
+#[cfg(feature = "tokio_util")]
+impl tokio_util::codec::Encoder<String> for Utf8Codec {
+    type Error = DecodeError;
+
+    fn encode(&mut self, item: String, dst: &mut BytesMut) -> Result<(), DecodeError> {
+        dst.extend_from_slice(item.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "asynchronous_codec")]
+impl asynchronous_codec::Decoder for Utf8Codec {
+    type Item = String;
+    type Error = DecodeError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<String>, DecodeError> {
+        Utf8Codec::decode(self, src)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<String>, DecodeError> {
+        Utf8Codec::decode_eof(self, src)
+    }
+}
+
+#[cfg(feature = "asynchronous_codec")]
+impl asynchronous_codec::Encoder for Utf8Codec {
+    type Item<'a> = String;
+    type Error = DecodeError;
+
+    fn encode(&mut self, item: String, dst: &mut BytesMut) -> Result<(), DecodeError> {
+        dst.extend_from_slice(item.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::prelude::*;
+
+    #[test]
+    fn codec_decode_across_reads() -> Result<()> {
+        let text = "$ü€💖".repeat(4);
+        let mut codec = Utf8Codec::new();
+        let mut src = BytesMut::new();
+        let mut decoded = String::new();
+        for chunk in text.as_bytes().chunks(3) {
+            src.extend_from_slice(chunk);
+            while let Some(s) = codec.decode(&mut src)? {
+                decoded.push_str(&s);
+            }
+        }
+        assert_eq!(text, decoded);
+
+        src.extend_from_slice(&[0xF0, 0x9F]);
+        assert_eq!(None, codec.decode(&mut src)?);
+        assert_eq!(2, src.len());
+        assert!(matches!(
+            codec.decode_eof(&mut src),
+            Err(DecodeError::IncompleteUtf8Sequence { offset: 40, .. })
+        ));
+        assert_eq!(None, codec.decode_eof(&mut src)?);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio_util")]
+    #[async_std::test]
+    async fn codec_tokio_framed() -> Result<()> {
+        use tokio_util::codec::{FramedRead, FramedWrite};
+
+        let reader = &[0x24, 0xFF, 0x24, 0xF0, 0x9F][..];
+        let codec = Utf8Codec::with_error_policy(ErrorPolicy::Replace);
+        let decoded = FramedRead::new(reader, codec).try_collect::<String>();
+        assert_eq!("$\u{FFFD}$\u{FFFD}", decoded.await?);
+
+        let mut writer = FramedWrite::new(Vec::new(), Utf8Codec::new());
+        writer.send("$ü€💖".to_string()).await?;
+        assert_eq!("$ü€💖".as_bytes(), writer.get_ref());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "asynchronous_codec")]
+    #[async_std::test]
+    async fn codec_asynchronous_framed() -> Result<()> {
+        use asynchronous_codec::{FramedRead, FramedWrite};
+
+        let reader = &[0x24, 0xFF, 0x24, 0xF0, 0x9F][..];
+        let codec = Utf8Codec::with_error_policy(ErrorPolicy::Replace);
+        let decoded = FramedRead::new(reader, codec).try_collect::<String>();
+        assert_eq!("$\u{FFFD}$\u{FFFD}", decoded.await?);
+
+        let mut writer = FramedWrite::new(Vec::new(), Utf8Codec::new());
+        writer.send("$ü€💖".to_string()).await?;
+        assert_eq!("$ü€💖".as_bytes(), writer.into_inner());
+
+        Ok(())
+    }
+}
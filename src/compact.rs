@@ -0,0 +1,84 @@
+//! Small string optimized output backed by `compact_str`.
+use crate::decoder::{Result, Utf8Decoder};
+use compact_str::CompactString;
+use futures_core::{ready, Stream};
+use futures_io::AsyncRead;
+use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Stream of [`CompactString`] items created by [`Utf8Decoder::into_compact`].
+    ///
+    /// Chunks short enough to be stored inline, which is typical for interactive
+    /// streams, are yielded without any heap allocation.
+    pub struct CompactStrings<R> {
+        #[pin]
+        decoder: Utf8Decoder<R>,
+    }
+}
+
+impl<R> CompactStrings<R> {
+    pub(crate) fn new(decoder: Utf8Decoder<R>) -> Self {
+        Self { decoder }
+    }
+
+    /// Consumes this stream, returning the underlying decoder.
+    pub fn into_inner(self) -> Utf8Decoder<R> {
+        self.decoder
+    }
+
+    /// Acquires a reference to the underlying decoder.
+    pub fn get_ref(&self) -> &Utf8Decoder<R> {
+        &self.decoder
+    }
+
+    /// Acquires a mutable reference to the underlying decoder.
+    pub fn get_mut(&mut self) -> &mut Utf8Decoder<R> {
+        &mut self.decoder
+    }
+}
+
+impl<R> Stream for CompactStrings<R>
+where
+    R: AsyncRead,
+{
+    type Item = Result<CompactString>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let mut decoder = self.project().decoder;
+        let result = ready!(decoder.as_mut().poll_decode(cx));
+        let buf = decoder.buffer_mut();
+        Poll::Ready(result.map(|r| {
+            r.map(|valid| {
+                let decoded = CompactString::new(buf.lend(valid));
+                buf.consume_lent();
+                decoded
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn compact_strings_decode() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> =
+            vec![Ok(vec![0x24, 0xF0, 0x9F]), Ok(vec![0x92, 0x96])];
+        let decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+        let decoded = decoder.into_compact().try_collect::<Vec<_>>().await?;
+
+        assert_eq!(vec!["$", "💖"], decoded);
+        assert!(decoded.iter().all(|s| !s.is_heap_allocated()));
+
+        Ok(())
+    }
+}
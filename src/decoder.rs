@@ -1,351 +1,2619 @@
-use crate::error::DecodeError;
+use crate::blocking::BlockingIter;
+#[cfg(feature = "http_body")]
+use crate::body::BodyStream;
+use crate::buffer::{Buffer, DropHook};
+use crate::builder::Utf8DecoderBuilder;
+use crate::chain::ChainReader;
+#[cfg(any(feature = "tokio", feature = "async_channel"))]
+use crate::channel::ReceiverStream;
+use crate::chunk::Utf8Chunks;
+#[cfg(feature = "compact_str")]
+use crate::compact::CompactStrings;
+use crate::err_into::ErrInto;
+use crate::error::{DecodeError, Limit};
+pub use crate::error::{ErrorPolicy, Result};
+#[cfg(feature = "timer")]
+use crate::idle::IdleEvents;
+use crate::io_stream::IoStream;
+use crate::meter::{Throughput, ThroughputMeter};
+use crate::observer::DecoderObserver;
+use crate::pool::BufferPool;
+use crate::sans_io::{step, Step};
+use crate::state::DecoderState;
+use crate::stats::DecoderStats;
+use crate::stream_reader::StreamReader;
+use crate::take::TakeReader;
+#[cfg(feature = "timer")]
+use crate::timer::Deadline;
+use crate::timer::{Expired, Timers};
+#[cfg(feature = "tokio")]
+use crate::tokio_io::TokioReader;
+use crate::validated;
+use crate::validation::replace_invalid;
+use crate::warning::{self, Warnings};
+#[cfg(feature = "wasm")]
+use crate::wasm::JsChunks;
+use futures_core::stream::{BoxStream, LocalBoxStream};
 use futures_core::{ready, Stream};
 use futures_io::AsyncRead;
 use pin_project_lite::pin_project;
+use std::fmt;
+use std::future::poll_fn;
+#[cfg(feature = "timer")]
+use std::future::Future;
+use std::io;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::task::{Context, Poll};
 
 const DEFAULT_BUF_SIZE: usize = 8 * 1024;
 const MINIMUM_BUF_SIZE: usize = 4; // Maximum utf-8 character byte length
+pub(crate) const READ_BUDGET: usize = 32; // Maximum number of reads in a single poll
+pub(crate) const INTERRUPTED_RETRIES: usize = 16; // Default number of retries of interrupted reads
+const LARGE_READ_SIZE: usize = 64 * 1024;
+const DEBUG_PREVIEW_SIZE: usize = 8; // Maximum number of pending bytes shown by Debug
 
-pub type Result<T> = std::result::Result<T, DecodeError>;
+static DEFAULT_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_BUF_SIZE);
+
+/// Returns the capacity of decoders created by [`Utf8Decoder::new`].
+pub fn default_capacity() -> usize {
+    DEFAULT_CAPACITY.load(Ordering::Relaxed)
+}
+
+/// Set the capacity of decoders created by [`Utf8Decoder::new`] from now on.
+///
+/// It is 8 KiB unless changed, and never goes below 4 bytes. Use it to pick a
+/// smaller default on memory-constrained targets or a larger one on servers
+/// without touching every construction site.
+pub fn set_default_capacity(capacity: usize) {
+    DEFAULT_CAPACITY.store(capacity.max(MINIMUM_BUF_SIZE), Ordering::Relaxed);
+}
+
+/// How much reading a single poll of the decoder may do
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReadStrategy {
+    /// Yield as soon as a read produced some text. This is the default.
+    #[default]
+    SingleRead,
+    /// Keep reading while the reader is ready, up to the given number of reads.
+    Budget(usize),
+    /// Keep reading until the reader would block, reaches the end, or the buffer is full.
+    UntilPending,
+}
+
+/// What the decoder makes of a read which returns no bytes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ZeroReadPolicy {
+    /// Take it for the end of the stream. This is the default.
+    #[default]
+    Eof,
+    /// Poll the reader again after waking the task, up to the given number of
+    /// consecutive zero-length reads, before taking it for the end of the
+    /// stream. For readers which return no bytes transiently.
+    Retry(usize),
+}
+
+/// Bounds of the automatic capacity tuning enabled by [`Utf8Decoder::set_autotune`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Autotune {
+    /// The capacity is never shrunk below this
+    pub min: usize,
+    /// The capacity is never grown above this
+    pub max: usize,
+}
 
 pin_project! {
+    /// Incremental UTF-8 decoder which turns an [`AsyncRead`] into a [`Stream`]
+    /// of strings.
+    ///
+    /// The decoder can be lent to a helper function and taken back afterwards,
+    /// as `&mut Utf8Decoder<R>` is a [`Stream`] itself when `R` is [`Unpin`].
+    /// Pin the decoder first, e.g. with [`std::pin::pin!`], and lend the
+    /// `Pin<&mut Utf8Decoder<R>>` for a reader which is not.
     pub struct Utf8Decoder<R> {
         #[pin]
         reader: R,
-        buf: Box<[u8]>,
-        remains: usize,
+        buf: Buffer,
+        // Output bytes for direct reads which is 'None' unless enabled
+        direct: Option<Vec<u8>>,
+        strategy: ReadStrategy,
+        policy: ErrorPolicy,
+        // Error encountered while draining which is reported on the next poll
+        error: Option<DecodeError>,
+        shrink: bool,
+        meter: Option<ThroughputMeter>,
+        // Set by 'close' or an exceeded limit to stop decoding until the decoder
+        // is reset
+        closed: bool,
+        // Length of the decoded prefix of the buffer held back by 'peek'
+        peeked: Option<usize>,
+        limit: TotalLimit,
+        timers: Timers,
+    }
+}
+
+// Limits of the total decoded text and the amount decoded so far
+#[derive(Debug, Default, Clone, Copy)]
+struct TotalLimit {
+    max_bytes: Option<usize>,
+    max_chars: Option<usize>,
+    bytes: usize,
+    chars: usize,
+}
+
+impl TotalLimit {
+    fn record(&mut self, decoded: &str) -> Result<()> {
+        self.bytes = self.bytes.saturating_add(decoded.len());
+        if let Some(max) = self.max_bytes {
+            if self.bytes > max {
+                return Err(DecodeError::LimitExceeded(Limit::Bytes(max)));
+            }
+        }
+        if let Some(max) = self.max_chars {
+            self.chars = self.chars.saturating_add(decoded.chars().count());
+            if self.chars > max {
+                return Err(DecodeError::LimitExceeded(Limit::Chars(max)));
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.bytes = 0;
+        self.chars = 0;
+    }
+}
+
+/// [`Utf8Decoder`] of a boxed reader, which decodes heterogeneous sources
+/// behind one concrete type.
+pub type BoxUtf8Decoder = Utf8Decoder<Box<dyn AsyncRead + Send + Unpin>>;
+
+impl BoxUtf8Decoder {
+    /// Create a new incremental UTF-8 decoder from `reader` boxed as a trait object
+    pub fn new_boxed<R>(reader: R) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        Utf8Decoder::new(Box::new(reader))
+    }
+}
+
+impl<S, B, E> Utf8Decoder<StreamReader<S, B>>
+where
+    S: Stream<Item = std::result::Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: Into<io::Error>,
+{
+    /// Create a new incremental UTF-8 decoder from a `stream` of byte chunks,
+    /// such as the receiver of a channel. See [`StreamReader`].
+    pub fn from_stream(stream: S) -> Self {
+        Utf8Decoder::new(StreamReader::new(stream))
+    }
+}
+
+#[cfg(feature = "http_body")]
+impl<B> Utf8Decoder<StreamReader<BodyStream<B>, bytes::Bytes>>
+where
+    B: http_body::Body,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    /// Create a new incremental UTF-8 decoder from an `http-body` body such as
+    /// the body of a `hyper` request. See [`BodyStream`].
+    pub fn from_body(body: B) -> Self {
+        Utf8Decoder::from_stream(BodyStream::new(body))
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Utf8Decoder<StreamReader<BodyStream<reqwest::Body>, bytes::Bytes>> {
+    /// Create a new incremental UTF-8 decoder from the body of a `reqwest`
+    /// response, which is read as `Response::bytes_stream` would read it.
+    ///
+    /// An error of the response is reported as [`DecodeError::IOError`] which
+    /// wraps the `reqwest::Error`.
+    pub fn from_response(response: reqwest::Response) -> Self {
+        Utf8Decoder::from_body(response.into())
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async_channel"))]
+impl<C> Utf8Decoder<StreamReader<ReceiverStream<C>>>
+where
+    ReceiverStream<C>: Stream<Item = io::Result<Vec<u8>>>,
+{
+    /// Create a new incremental UTF-8 decoder from the receiver of a channel
+    /// of byte chunks. See [`ReceiverStream`] for the supported receivers.
+    pub fn from_receiver(rx: C) -> Self {
+        Utf8Decoder::from_stream(ReceiverStream::new(rx))
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl Utf8Decoder<StreamReader<JsChunks>> {
+    /// Create a new incremental UTF-8 decoder from a JavaScript
+    /// `ReadableStream` of bytes, such as the body of a response of `fetch`.
+    /// See [`JsChunks`].
+    pub fn from_readable_stream(stream: wasm_streams::readable::sys::ReadableStream) -> Self {
+        Utf8Decoder::from_stream(JsChunks::new(stream))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R> Utf8Decoder<TokioReader<R>>
+where
+    R: tokio::io::AsyncRead,
+{
+    /// Create a new incremental UTF-8 decoder from a [`tokio::io::AsyncRead`]
+    /// `reader`, without the compat layer of `tokio-util`.
+    pub fn from_tokio(reader: R) -> Self {
+        Utf8Decoder::new(TokioReader::new(reader))
+    }
+}
+
+impl Utf8Decoder<()> {
+    /// Returns a builder which collects the options of a decoder before
+    /// wrapping a reader.
+    pub fn builder() -> Utf8DecoderBuilder {
+        Utf8DecoderBuilder::new()
     }
 }
 
 impl<R> Utf8Decoder<R> {
     /// Create a new incremental UTF-8 decoder from `reader`
     pub fn new(reader: R) -> Self {
-        Utf8Decoder::with_capacity(DEFAULT_BUF_SIZE, reader)
+        Utf8Decoder::with_capacity(default_capacity(), reader)
+    }
+
+    /// Create a new incremental UTF-8 decoder from `reader` with specified capacity
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is smaller than 4 bytes, the maximum length of a
+    /// UTF-8 sequence. Use [`Utf8Decoder::try_with_capacity`] to handle it.
+    pub fn with_capacity(capacity: usize, reader: R) -> Self {
+        match Utf8Decoder::try_with_capacity(capacity, reader) {
+            Ok(decoder) => decoder,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Create a new incremental UTF-8 decoder from `reader` with specified
+    /// capacity, or fail with [`DecodeError::CapacityTooSmall`].
+    pub fn try_with_capacity(capacity: usize, reader: R) -> Result<Self> {
+        check_capacity(capacity)?;
+        Ok(Utf8Decoder::from_buffer(
+            Buffer::with_capacity(capacity),
+            reader,
+        ))
+    }
+
+    /// Create a new incremental UTF-8 decoder from `reader` which decodes
+    /// `bytes` already read from the transport before anything else.
+    ///
+    /// The bytes may end in the middle of a sequence which continues in the
+    /// reader.
+    pub fn with_initial_bytes(bytes: &[u8], reader: R) -> Self {
+        let mut decoder = Utf8Decoder::new(reader);
+        decoder.set_initial_bytes(bytes);
+        decoder
+    }
+
+    pub(crate) fn set_initial_bytes(&mut self, bytes: &[u8]) {
+        self.buf.splice(0, bytes);
+    }
+
+    /// Create a new incremental UTF-8 decoder from `reader` with an internal
+    /// buffer drawn from `pool`.
+    ///
+    /// The buffer is returned to the pool when the decoder is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer size of `pool` is smaller than 4 bytes.
+    pub fn with_pool(pool: &BufferPool, reader: R) -> Self {
+        if let Err(err) = check_capacity(pool.buffer_size()) {
+            panic!("{}", err);
+        }
+        Utf8Decoder::from_buffer(Buffer::with_pool(pool), reader)
+    }
+
+    fn from_buffer(buf: Buffer, reader: R) -> Self {
+        Self {
+            reader,
+            buf,
+            direct: None,
+            strategy: ReadStrategy::SingleRead,
+            policy: ErrorPolicy::Strict,
+            error: None,
+            shrink: false,
+            meter: None,
+            closed: false,
+            peeked: None,
+            limit: TotalLimit::default(),
+            timers: Timers::default(),
+        }
+    }
+
+    /// Returns the capacity of the internal buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Change the capacity of the internal buffer.
+    ///
+    /// Bytes of a pending partial sequence are kept, so the buffer can be grown
+    /// for a bulk transfer and shrunk back afterwards without recreating the
+    /// decoder. The capacity is never reduced below 4 bytes.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.buf.set_capacity(capacity.max(MINIMUM_BUF_SIZE));
+    }
+
+    /// Grow the capacity of the internal buffer by `additional` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`, as [`Vec::reserve`] does.
+    pub fn reserve(&mut self, additional: usize) {
+        let capacity = self
+            .buf
+            .capacity()
+            .checked_add(additional)
+            .expect("capacity overflow");
+        self.buf.set_capacity(capacity);
+    }
+
+    /// Returns the number of bytes currently held in the internal buffer.
+    ///
+    /// It is the length of [`Utf8Decoder::pending_bytes`].
+    pub fn buffer_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the bytes which have been read but not decoded yet.
+    ///
+    /// These are usually the leading bytes of a sequence which continues in the
+    /// next read.
+    pub fn pending_bytes(&self) -> &[u8] {
+        self.buf.pending()
+    }
+
+    /// Take the bytes which have been read but not decoded yet out of the
+    /// decoder.
+    ///
+    /// Decoding goes on with the next read as if they had never been read,
+    /// which allows to switch the protocol of the reader mid-stream.
+    pub fn take_remainder(&mut self) -> Vec<u8> {
+        self.peeked = None;
+        self.buf.consume_lent();
+        let remainder = self.buf.filled().to_vec();
+        self.buf.truncate(0);
+        remainder
+    }
+
+    /// Returns the maximum number of bytes the internal buffer has held.
+    ///
+    /// Compare it with [`Utf8Decoder::capacity`] to right-size the buffer from
+    /// real traffic.
+    pub fn high_water_mark(&self) -> usize {
+        self.buf.high_water()
+    }
+
+    /// Reset the high-water mark to the current fill level.
+    pub fn reset_high_water_mark(&mut self) {
+        self.buf.reset_high_water()
+    }
+
+    /// Let the decoder adjust the capacity of the internal buffer to the traffic.
+    ///
+    /// When enabled, the capacity is doubled after consecutive reads filled the
+    /// buffer and halved after a sustained period of small reads, staying within
+    /// the given bounds. Pass `None` to keep the current capacity from now on.
+    pub fn set_autotune(&mut self, autotune: Option<Autotune>) {
+        let autotune = autotune.map(|Autotune { min, max }| {
+            let min = min.max(MINIMUM_BUF_SIZE);
+            Autotune {
+                min,
+                max: max.max(min),
+            }
+        });
+        self.buf.set_autotune(autotune);
+    }
+
+    /// Read directly into the output `String` of the [`Stream`] implementation.
+    ///
+    /// When enabled and no partial sequence is pending, reads go straight into
+    /// the allocation which becomes the next item, so ASCII-heavy streams skip
+    /// the copy out of the internal buffer. Only an incomplete sequence at the
+    /// end of a read is moved to the internal buffer. Items may have excess
+    /// capacity up to the read size in this mode.
+    pub fn set_direct_read(&mut self, enabled: bool) {
+        self.direct = if enabled { Some(Vec::new()) } else { None };
+    }
+
+    /// Set how much reading a single poll may do.
+    ///
+    /// Strategies other than [`ReadStrategy::SingleRead`] keep reading while the
+    /// reader is ready and produce fewer and larger items. An error encountered
+    /// after some text has been decoded is reported on the following poll. Direct
+    /// reads are only used with [`ReadStrategy::SingleRead`].
+    pub fn set_read_strategy(&mut self, strategy: ReadStrategy) {
+        self.strategy = strategy;
+    }
+
+    /// Set what a read which returns no bytes means. See [`ZeroReadPolicy`].
+    pub fn set_zero_read_policy(&mut self, policy: ZeroReadPolicy) {
+        self.buf.set_zero_read_policy(policy);
+    }
+
+    /// Set how many consecutive reads which fail with `ErrorKind::Interrupted`
+    /// are retried before the error is yielded.
+    ///
+    /// Interrupted reads are retried right away as `std::io::Read` users do.
+    /// It defaults to 16, and `0` yields every such error.
+    pub fn set_interrupted_retries(&mut self, retries: usize) {
+        self.buf.set_interrupted_retries(retries);
+    }
+
+    /// Take reads which fail with `ErrorKind::WouldBlock` for pending instead of
+    /// yielding the error, which would end most consumers of the stream.
+    ///
+    /// It is for readers which report that they are not ready with the error
+    /// instead of `Poll::Pending`. As such a reader does not wake the task when
+    /// it becomes ready, the task is woken right away to poll it again.
+    pub fn set_would_block_as_pending(&mut self, enabled: bool) {
+        self.buf.set_would_block_as_pending(enabled);
+    }
+
+    /// Tune the decoder for bulk decoding of large inputs such as files.
+    ///
+    /// The buffer grows to at least 64 KiB and is read into in full from the
+    /// first read, and the decoder keeps reading until the buffer is full or the
+    /// reader would block (see [`ReadStrategy::UntilPending`]). Items are then
+    /// mostly as large as the buffer, and such items take over the storage of
+    /// the buffer instead of being copied. Enable the `nightly` feature as well
+    /// to scan ASCII with SIMD.
+    pub fn enable_large_reads(&mut self) {
+        self.set_capacity(self.capacity().max(LARGE_READ_SIZE));
+        self.buf.open_window();
+        self.strategy = ReadStrategy::UntilPending;
+    }
+
+    /// Shrink the capacity of each item of the [`Stream`] implementation to fit.
+    ///
+    /// Items may otherwise hold excess capacity up to the buffer size, which is
+    /// wasted while they are queued downstream. Shrinking costs a reallocation
+    /// for such items.
+    pub fn set_shrink_to_fit(&mut self, enabled: bool) {
+        self.shrink = enabled;
+    }
+
+    /// Call `f` with the bytes which have been read but not decoded yet if the
+    /// decoder is dropped while holding any.
+    ///
+    /// Such bytes are otherwise lost silently, for example when the decoder is
+    /// dropped in the middle of a multi-byte sequence or consumed with
+    /// [`Utf8Decoder::into_inner`]. Bytes taken with [`Utf8Decoder::into_parts`]
+    /// or [`Utf8Decoder::close`] and those cleared by [`Utf8Decoder::reset`] are
+    /// not reported. A previously registered callback is replaced.
+    pub fn on_drop_remainder<F>(&mut self, f: F)
+    where
+        F: FnOnce(&[u8]) + Send + Sync + 'static,
+    {
+        self.set_drop_hook(Some(DropHook::new(f)));
+    }
+
+    pub(crate) fn set_drop_hook(&mut self, hook: Option<DropHook>) {
+        self.buf.set_drop_hook(hook);
+    }
+
+    /// Remove the drop callback, the observer, and the warnings, which belong
+    /// to the connection rather than to the configuration
+    pub(crate) fn detach(&mut self) {
+        self.set_drop_hook(None);
+        self.buf.set_observer(None);
+        self.buf.set_warnings(None);
+    }
+
+    /// Limit the total bytes of text the decoder yields, or remove the limit
+    /// with `None`.
+    ///
+    /// Once the limit would be exceeded, the item which crosses it is
+    /// discarded, [`DecodeError::LimitExceeded`] is yielded, and the stream
+    /// ends. It puts a hard ceiling on how much text a single connection can
+    /// make a service allocate. The count is cleared by [`Utf8Decoder::reset`].
+    pub fn set_max_total_bytes(&mut self, max: Option<usize>) {
+        self.limit.max_bytes = max;
+    }
+
+    /// Same as [`Utf8Decoder::set_max_total_bytes`] but limits the total
+    /// characters.
+    pub fn set_max_total_chars(&mut self, max: Option<usize>) {
+        self.limit.max_chars = max;
+    }
+
+    /// Yield [`DecodeError::Timeout`] when the reader has not returned any bytes
+    /// for `timeout`, or wait forever with `None`.
+    ///
+    /// The decoder keeps working after the error, so the consumer decides
+    /// whether to give up on a stalled reader or to wait for another period.
+    #[cfg(feature = "timer")]
+    pub fn set_read_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.timers.read = timeout.map(Deadline::new);
+    }
+
+    /// Give up on an incomplete sequence which has not been completed within
+    /// `timeout`, or wait forever with `None`.
+    ///
+    /// The sequence is dropped and dealt with as an incomplete sequence at the
+    /// end of the input according to the [`ErrorPolicy`], so a peer which sends
+    /// a lone lead byte does not wedge the stream silently.
+    #[cfg(feature = "timer")]
+    pub fn set_incomplete_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.timers.incomplete = timeout.map(Deadline::new);
+    }
+
+    /// Measure the decode throughput with `meter`, or stop measuring with `None`.
+    pub fn set_throughput_meter(&mut self, meter: Option<ThroughputMeter>) {
+        self.meter = meter;
+    }
+
+    /// Notify `observer` of the events in this decoder, or stop notifying with
+    /// `None`.
+    ///
+    /// Unlike the statistics, the observer is kept by [`Utf8Decoder::reset`].
+    pub fn set_observer(&mut self, observer: Option<Box<dyn DecoderObserver + Send + Sync>>) {
+        self.buf.set_observer(observer);
+    }
+
+    /// Returns a stream of the invalid sequences which are replaced or skipped
+    /// from now on, according to the [`ErrorPolicy`]. See [`Warnings`].
+    ///
+    /// It keeps the text stream clean of anything but text while the invalid
+    /// input is still observable. A stream returned earlier ends.
+    pub fn warnings(&mut self) -> Warnings {
+        let (sender, warnings) = warning::channel();
+        self.buf.set_warnings(Some(sender));
+        warnings
+    }
+
+    /// Returns the cumulative statistics of this decoder.
+    pub fn stats(&self) -> DecoderStats {
+        self.buf.stats()
+    }
+
+    /// Returns the decode throughput over the window of the meter set by
+    /// [`Utf8Decoder::set_throughput_meter`], if any.
+    pub fn throughput(&mut self) -> Option<Throughput> {
+        self.meter.as_mut().map(|meter| meter.throughput())
+    }
+
+    /// Set how invalid and truncated input is dealt with.
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.policy = policy;
+    }
+
+    /// Keep reading while the reader is ready before yielding an item.
+    ///
+    /// This is a shorthand of [`ReadStrategy::Budget`] with a budget which avoids
+    /// starving other tasks, or [`ReadStrategy::SingleRead`] when disabled.
+    pub fn set_eager_drain(&mut self, enabled: bool) {
+        self.strategy = if enabled {
+            ReadStrategy::Budget(READ_BUDGET)
+        } else {
+            ReadStrategy::SingleRead
+        };
+    }
+
+    /// Replace the underlying reader with `reader` and return the old one.
+    ///
+    /// Pending bytes, a stashed error, and statistics are dropped while the
+    /// configuration and the allocated buffer are kept, so a decoder can be
+    /// reused for a new connection. Use [`Utf8Decoder::set_reader`] to resume
+    /// the same stream from another reader instead.
+    pub fn reset(&mut self, reader: R) -> R {
+        self.clear();
+        std::mem::replace(&mut self.reader, reader)
+    }
+
+    /// Replace the underlying reader with `reader` and return the old one,
+    /// keeping everything else.
+    ///
+    /// Unlike [`Utf8Decoder::reset`], the pending partial sequence, a stashed
+    /// error, and statistics survive the swap, so a client which reconnects in
+    /// the middle of a character resumes decoding from the new connection as
+    /// if the stream had never been interrupted.
+    pub fn set_reader(&mut self, reader: R) -> R {
+        std::mem::replace(&mut self.reader, reader)
+    }
+
+    /// Same as [`Utf8Decoder::set_reader`] but maps the underlying reader with
+    /// `f`, which allows the reader type to change.
+    pub fn map_reader<S, F>(self, f: F) -> Utf8Decoder<S>
+    where
+        F: FnOnce(R) -> S,
+    {
+        let (decoder, reader) = self.swap_reader(());
+        decoder.swap_reader(f(reader)).0
+    }
+
+    /// Consumes this decoder, returning a decoder which reads `other` once the
+    /// underlying reader is closed. See [`ChainReader`].
+    ///
+    /// A sequence split at the end of the underlying reader is completed by the
+    /// start of `other`, so an input split at arbitrary byte offsets, such as a
+    /// multi-part upload, decodes as a whole. It must be called before the end
+    /// of the underlying reader has been decoded, since the incomplete sequence
+    /// is reported as an error then.
+    pub fn chain<S>(self, other: S) -> Utf8Decoder<ChainReader<R, S>> {
+        self.map_reader(|reader| ChainReader::new(reader, other))
+    }
+
+    /// Consumes this decoder, returning a decoder which stops reading after `n`
+    /// more bytes of the underlying reader. See [`TakeReader`].
+    ///
+    /// The stream ends at the last complete character within the limit, and a
+    /// sequence cut off by the limit is reported as
+    /// [`DecodeError::IncompleteUtf8Sequence`]. It allows to decode exactly one
+    /// length-prefixed text section of a larger binary stream, whose rest is
+    /// left in the underlying reader. Call it on a new decoder, since bytes
+    /// already read into the buffer do not count.
+    pub fn take_bytes(self, n: u64) -> Utf8Decoder<TakeReader<R>> {
+        self.map_reader(|reader| TakeReader::new(reader, n))
+    }
+
+    /// Same as [`Utf8Decoder::reset`] but allows the reader type to change.
+    pub(crate) fn replace_reader<S>(self, reader: S) -> (Utf8Decoder<S>, R) {
+        let (mut decoder, old) = self.swap_reader(reader);
+        decoder.clear();
+        (decoder, old)
+    }
+
+    fn swap_reader<S>(self, reader: S) -> (Utf8Decoder<S>, R) {
+        let Utf8Decoder {
+            reader: old,
+            buf,
+            direct,
+            strategy,
+            policy,
+            error,
+            shrink,
+            meter,
+            closed,
+            peeked,
+            limit,
+            timers,
+        } = self;
+        let decoder = Utf8Decoder {
+            reader,
+            buf,
+            direct,
+            strategy,
+            policy,
+            error,
+            shrink,
+            meter,
+            closed,
+            peeked,
+            limit,
+            timers,
+        };
+        (decoder, old)
+    }
+
+    fn clear(&mut self) {
+        self.buf.clear();
+        if let Some(direct) = &mut self.direct {
+            direct.clear();
+        }
+        self.error = None;
+        self.closed = false;
+        self.peeked = None;
+        self.limit.clear();
+    }
+
+    /// Create a new incremental UTF-8 decoder from `reader` and the undecoded
+    /// bytes returned by [`Utf8Decoder::into_parts`].
+    ///
+    /// The bytes are decoded before anything else is read from `reader`.
+    pub fn from_parts(reader: R, remainder: Vec<u8>) -> Self {
+        Utf8Decoder::with_initial_bytes(&remainder, reader)
+    }
+
+    /// Create a new incremental UTF-8 decoder from `reader` with the `state`
+    /// exported by [`Utf8Decoder::state`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity in `state` is smaller than 4 bytes.
+    pub fn from_state(state: DecoderState, reader: R) -> Self {
+        let mut decoder = Utf8Decoder::with_capacity(state.capacity, reader);
+        decoder.set_read_strategy(state.read_strategy);
+        decoder.set_error_policy(state.error_policy);
+        decoder.set_autotune(state.autotune);
+        decoder.set_direct_read(state.direct_read);
+        decoder.set_shrink_to_fit(state.shrink_to_fit);
+        decoder.set_initial_bytes(&state.remainder);
+        decoder.buf.restore_high_water(state.high_water_mark);
+        decoder
+    }
+
+    /// Export the state of this decoder apart from the reader.
+    ///
+    /// A stashed error and the throughput meter are not part of the state.
+    pub fn state(&self) -> DecoderState {
+        DecoderState {
+            remainder: self.pending_bytes().to_vec(),
+            capacity: self.capacity(),
+            high_water_mark: self.high_water_mark(),
+            read_strategy: self.strategy,
+            error_policy: self.policy,
+            autotune: self.buf.autotune(),
+            direct_read: self.direct.is_some(),
+            shrink_to_fit: self.shrink,
+        }
+    }
+
+    /// Stop decoding and return the bytes which have been read but not decoded
+    /// yet.
+    ///
+    /// The stream is terminated in place: it yields nothing from now on, even
+    /// if the reader has more to read, while the reader stays in the decoder.
+    /// It can be reached with [`Utf8Decoder::get_mut`] or taken back with
+    /// [`Utf8Decoder::into_inner`] and handed over to another protocol, for
+    /// example after a `STARTTLS` switch, without losing any data. It works on
+    /// a decoder which is borrowed or pinned, where the decoder cannot be
+    /// consumed with [`Utf8Decoder::into_parts`]. A stashed error is dropped.
+    /// Use [`Utf8Decoder::reset`] to decode again.
+    pub fn close(&mut self) -> Vec<u8> {
+        self.error = None;
+        self.closed = true;
+        self.take_remainder()
+    }
+
+    /// Returns `true` if [`Utf8Decoder::close`] has been called.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Consumes this decoder, returning the underlying reader and the bytes
+    /// which have been read but not decoded yet.
+    ///
+    /// Unlike [`Utf8Decoder::into_inner`], no data is lost, so the reader can
+    /// be handed to another subsystem along with the bytes.
+    pub fn into_parts(mut self) -> (R, Vec<u8>) {
+        let remainder = self.take_remainder();
+        (self.reader, remainder)
+    }
+
+    /// Consumes this decoder, returning the underlying reader.
+    ///
+    /// Bytes which have been read but not decoded yet are discarded. Use
+    /// [`Utf8Decoder::into_parts`] to keep them.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Acquires a reference to the underlying reader that this
+    /// decoder is pulling from.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader that
+    /// this decoder is pulling from.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R> Utf8Decoder<R>
+where
+    R: AsyncRead,
+{
+    /// Consumes this decoder, returning a stream of zero-copy [`Utf8Chunk`] items.
+    pub fn into_chunks(self) -> Utf8Chunks<R> {
+        Utf8Chunks::new(self)
+    }
+
+    /// Consumes this decoder, returning a stream of small string optimized items.
+    #[cfg(feature = "compact_str")]
+    pub fn into_compact(self) -> CompactStrings<R> {
+        CompactStrings::new(self)
+    }
+
+    /// Consumes this decoder, returning a stream which yields `io::Result`
+    /// items. See [`IoStream`].
+    pub fn into_io_stream(self) -> IoStream<R> {
+        self.err_into()
+    }
+
+    /// Consumes this decoder, returning a stream which converts each error
+    /// into `E` with `From`. See [`ErrInto`].
+    pub fn err_into<E>(self) -> ErrInto<R, E>
+    where
+        E: From<DecodeError>,
+    {
+        ErrInto::new(self)
+    }
+
+    /// Consumes this decoder, returning a stream which injects
+    /// [`DecoderEvent::Idle`] when no bytes have arrived for `idle`.
+    ///
+    /// [`DecoderEvent::Idle`]: crate::DecoderEvent::Idle
+    #[cfg(feature = "timer")]
+    pub fn into_idle_events(self, idle: std::time::Duration) -> IdleEvents<R> {
+        IdleEvents::new(self, idle)
+    }
+
+    /// Consumes this decoder, returning it as a boxed [`Stream`] trait object.
+    ///
+    /// Decoders of differently typed readers can then be stored in one
+    /// collection.
+    pub fn boxed<'a>(self) -> BoxStream<'a, Result<String>>
+    where
+        R: Send + 'a,
+    {
+        Box::pin(self)
+    }
+
+    /// Same as [`Utf8Decoder::boxed`] but for a reader which is not [`Send`].
+    pub fn boxed_local<'a>(self) -> LocalBoxStream<'a, Result<String>>
+    where
+        R: 'a,
+    {
+        Box::pin(self)
+    }
+
+    /// Attempt to decode the next non-empty valid prefix into the buffer and
+    /// return its length.
+    pub(crate) fn poll_decode(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<usize>>> {
+        if let Some(valid) = self.as_mut().project().peeked.take() {
+            return Poll::Ready(Some(Ok(valid)));
+        }
+        let reads = self.buf.stats().reads;
+        let result = match self.as_mut().poll_fill(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => {
+                let this = self.as_mut().project();
+                let progressed = this.buf.stats().reads != reads;
+                let incomplete = !this.buf.filled().is_empty();
+                match ready!(this.timers.poll_pending(cx, progressed, incomplete)) {
+                    Expired::Read(err) => return Poll::Ready(Some(Err(err))),
+                    Expired::Incomplete => match drop_incomplete(this.buf, *this.policy) {
+                        Some(result) => Some(result),
+                        None => {
+                            cx.waker().wake_by_ref();
+                            return Poll::Pending;
+                        }
+                    },
+                }
+            }
+        };
+        let this = self.project();
+        this.timers.on_ready();
+        if let Some(Ok(valid)) = &result {
+            this.buf.record_decoded(*valid);
+            if let Some(meter) = this.meter {
+                meter.record(*valid);
+            }
+            let decoded = validated::to_str(&this.buf.filled()[..*valid]);
+            if let Err(err) = this.limit.record(decoded) {
+                // Lending the item discards it on the next poll
+                this.buf.lend(*valid);
+                *this.closed = true;
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+        Poll::Ready(result)
+    }
+
+    fn poll_fill(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<usize>>> {
+        let mut this = self.project();
+        let buf = this.buf;
+        buf.consume_lent();
+        // An error held back by peek may be the one which closed the decoder
+        if let Some(err) = this.error.take() {
+            return Poll::Ready(Some(Err(err)));
+        }
+        if *this.closed {
+            return Poll::Ready(None);
+        }
+        let (eager, mut budget) = match *this.strategy {
+            ReadStrategy::SingleRead => (false, READ_BUDGET),
+            ReadStrategy::Budget(n) => (true, n.max(1)),
+            ReadStrategy::UntilPending => (true, usize::MAX),
+        };
+        // Bytes left from a previous read are normally an incomplete sequence,
+        // but bytes handed over with the reader may be decodable on their own.
+        let mut valid = 0;
+        if !buf.filled().is_empty() {
+            match decode_filled(buf, 0, *this.policy) {
+                Ok(v) => valid = v,
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+        loop {
+            if valid > 0 && (!eager || buf.is_full() || budget == 0) {
+                return Poll::Ready(Some(Ok(valid)));
+            }
+            if budget == 0 {
+                // Let other tasks run rather than spinning on a reader which is
+                // always ready, and come back right after.
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            budget -= 1;
+            let reader = this.reader.as_mut();
+            match decode_next(reader, cx, buf, valid, *this.policy) {
+                // Yield what has been drained once the reader would block
+                Poll::Pending if valid > 0 => return Poll::Ready(Some(Ok(valid))),
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(v))) => valid = v,
+                Poll::Ready(Some(Err(err))) if valid > 0 => {
+                    *this.error = Some(err);
+                    return Poll::Ready(Some(Ok(valid)));
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) if valid > 0 => return Poll::Ready(Some(Ok(valid))),
+                Poll::Ready(None) => {
+                    if !buf.filled().is_empty() {
+                        let replacement = this.policy.replacement();
+                        buf.record_invalid(1, replacement.unwrap_or_default());
+                        let Some(replacement) = replacement else {
+                            let remains = buf.filled().to_vec();
+                            let err = DecodeError::incomplete_at(remains, buf.offset());
+                            return Poll::Ready(Some(Err(err)));
+                        };
+                        buf.warn(buf.offset(), buf.filled(), replacement);
+                        buf.splice(0, replacement);
+                        if !replacement.is_empty() {
+                            return Poll::Ready(Some(Ok(replacement.len())));
+                        }
+                    }
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn buffer_mut(self: Pin<&mut Self>) -> &mut Buffer {
+        self.project().buf
+    }
+}
+
+impl<R> Utf8Decoder<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Attempt to decode the next chunk, registering the current task for wakeup
+    /// if it is not ready yet.
+    ///
+    /// It is the same as [`Stream::poll_next`] but callable on `&mut self`, so
+    /// hand-written futures can drive the decoder inside their own `poll`.
+    pub fn poll_next_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<String>>> {
+        Pin::new(self).poll_next(cx)
+    }
+
+    /// Decode the next chunk, or yield [`DecodeError::Timeout`] if it does not
+    /// arrive within `timeout`.
+    ///
+    /// It is cancellation safe, so the decoder can be polled again after a
+    /// timeout without losing any bytes, including a partial sequence.
+    #[cfg(feature = "timer")]
+    pub async fn next_timeout(&mut self, timeout: std::time::Duration) -> Option<Result<String>> {
+        let mut delay = futures_timer::Delay::new(timeout);
+        poll_fn(|cx| match self.poll_next_chunk(cx) {
+            Poll::Ready(item) => Poll::Ready(item),
+            Poll::Pending => {
+                ready!(Pin::new(&mut delay).poll(cx));
+                Poll::Ready(Some(Err(DecodeError::Timeout(timeout))))
+            }
+        })
+        .await
+    }
+
+    /// Consumes this decoder, returning an [`Iterator`] which blocks the current
+    /// thread until each item is decoded. See [`BlockingIter`].
+    ///
+    /// It allows synchronous code such as tests and scripts to consume the
+    /// decoder without an executor.
+    pub fn into_blocking_iter(self) -> BlockingIter<R> {
+        BlockingIter::new(self)
+    }
+
+    /// Decode the next chunk and return it as a borrow into the internal buffer.
+    ///
+    /// The returned string slice is valid until the next call on this decoder, so
+    /// consumers which process chunks immediately avoid any allocation.
+    pub async fn next_str(&mut self) -> Option<Result<&str>> {
+        let result = poll_fn(|cx| Pin::new(&mut *self).poll_decode(cx)).await;
+        match result? {
+            Ok(valid) => Some(Ok(self.buf.lend(valid))),
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    /// Decode the next chunk and return it without consuming it.
+    ///
+    /// The chunk is held back inside the decoder and yielded by the next call of
+    /// any other method which decodes, so the consumer can inspect the first
+    /// chunk, for example to decide which handler receives the stream. An error
+    /// is held back the same way.
+    pub async fn peek(&mut self) -> Option<std::result::Result<&str, &DecodeError>> {
+        if self.peeked.is_none() && self.error.is_none() {
+            let result = poll_fn(|cx| Pin::new(&mut *self).poll_decode(cx)).await;
+            match result? {
+                Ok(valid) => self.peeked = Some(valid),
+                Err(err) => self.error = Some(err),
+            }
+        }
+        match (self.peeked, &self.error) {
+            (Some(valid), _) => Some(Ok(validated::to_str(&self.buf.filled()[..valid]))),
+            (None, Some(err)) => Some(Err(err)),
+            (None, None) => None,
+        }
+    }
+
+    /// Decode the next chunk and append it to `out`, returning the number of
+    /// bytes appended.
+    ///
+    /// `Ok(0)` is returned once the underlying reader has been closed, so a single
+    /// `String` can be reused across chunks.
+    pub async fn next_into(&mut self, out: &mut String) -> Result<usize> {
+        let result = poll_fn(|cx| Pin::new(&mut *self).poll_decode(cx)).await;
+        match result {
+            Some(Ok(valid)) => {
+                out.push_str(self.buf.lend(valid));
+                Ok(valid)
+            }
+            Some(Err(err)) => Err(err),
+            None => Ok(0),
+        }
+    }
+
+    /// Decode everything until the underlying reader is closed and return it as
+    /// a single `String`.
+    ///
+    /// It stops at the first error, which is returned instead. See also
+    /// [`decode_all`].
+    pub async fn read_to_string(&mut self) -> Result<String> {
+        let mut out = String::new();
+        while self.next_into(&mut out).await? > 0 {}
+        Ok(out)
     }
 
-    /// Create a new incremental UTF-8 decoder from `reader` with specified capacity
-    pub fn with_capacity(capacity: usize, reader: R) -> Self {
-        debug_assert!(
-            capacity >= MINIMUM_BUF_SIZE,
-            "capacity must be at least {} but {} is specified",
-            MINIMUM_BUF_SIZE,
-            capacity,
+    /// Same as [`Utf8Decoder::read_to_string`] but fails with
+    /// [`DecodeError::LimitExceeded`] once the text would exceed `max_bytes`.
+    ///
+    /// The chunk which crosses the limit is not appended, so no more than
+    /// `max_bytes` are allocated for the text of an unbounded reader.
+    pub async fn collect_string_limited(&mut self, max_bytes: usize) -> Result<String> {
+        let mut out = String::new();
+        while let Some(chunk) = self.next_str().await {
+            let chunk = chunk?;
+            if out.len() + chunk.len() > max_bytes {
+                return Err(DecodeError::LimitExceeded(Limit::Bytes(max_bytes)));
+            }
+            out.push_str(chunk);
+        }
+        Ok(out)
+    }
+}
+
+/// Decode everything `reader` yields into a single `String`.
+///
+/// It fails on invalid UTF-8 with [`DecodeError`], which tells the invalid or
+/// incomplete bytes. Pin a reader which is not [`Unpin`] with
+/// [`std::pin::pin!`] and pass the pinned reference instead.
+pub async fn decode_all<R>(reader: R) -> Result<String>
+where
+    R: AsyncRead + Unpin,
+{
+    Utf8Decoder::new(reader).read_to_string().await
+}
+
+/// Decode everything `reader` yields into a single `String`, replacing invalid
+/// input with U+FFFD, and return it along with the number of replacements.
+///
+/// Only an I/O error of the reader is returned as an error.
+pub async fn decode_to_string_lossy<R>(reader: R) -> Result<(String, usize)>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut decoder = Utf8Decoder::new(reader);
+    decoder.set_error_policy(ErrorPolicy::Replace);
+    let decoded = decoder.read_to_string().await?;
+    Ok((decoded, decoder.stats().replacements as usize))
+}
+
+impl<R> fmt::Debug for Utf8Decoder<R>
+where
+    R: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Utf8Decoder")
+            .field("reader", &self.reader)
+            .field("capacity", &self.capacity())
+            .field("buffer_len", &self.buffer_len())
+            .field("pending", &Preview(self.pending_bytes()))
+            .field("high_water_mark", &self.high_water_mark())
+            .field("stats", &self.stats())
+            .field("read_strategy", &self.strategy)
+            .field("error_policy", &self.policy)
+            .field("autotune", &self.buf.autotune())
+            .field("direct_read", &self.direct.is_some())
+            .field("shrink_to_fit", &self.shrink)
+            .field("throughput_meter", &self.meter)
+            .field("error", &self.error)
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+
+/// Leading bytes of a buffer which is truncated in the output of Debug
+struct Preview<'a>(&'a [u8]);
+
+impl fmt::Debug for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown = self.0.len().min(DEBUG_PREVIEW_SIZE);
+        let mut list = f.debug_list();
+        list.entries(self.0[..shown].iter().map(|b| format!("{:#04X}", b)));
+        if shown < self.0.len() {
+            list.entry(&format_args!("... {} more", self.0.len() - shown));
+        }
+        list.finish()
+    }
+}
+
+impl<R> Stream for Utf8Decoder<R>
+where
+    R: AsyncRead,
+{
+    type Item = Result<String>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let shrink = self.shrink;
+        let shrink = |mut decoded: String| {
+            if shrink {
+                decoded.shrink_to_fit();
+            }
+            decoded
+        };
+        let this = self.as_mut().project();
+        if let Some(out) = this.direct {
+            let single = *this.strategy == ReadStrategy::SingleRead;
+            let idle = !*this.closed
+                && this.error.is_none()
+                && this.peeked.is_none()
+                && this.buf.filled().is_empty();
+            if single && idle {
+                let reads = this.buf.stats().reads;
+                let result = match decode_direct(this.reader, cx, this.buf, out, *this.policy) {
+                    Poll::Ready(result) => result,
+                    Poll::Pending => {
+                        // An incomplete sequence is never left behind on this path
+                        let progressed = this.buf.stats().reads != reads;
+                        match ready!(this.timers.poll_pending(cx, progressed, false)) {
+                            Expired::Read(err) => return Poll::Ready(Some(Err(err))),
+                            Expired::Incomplete => unreachable!(),
+                        }
+                    }
+                };
+                match result {
+                    // The read ended with an incomplete sequence which has been
+                    // moved to the internal buffer
+                    Some(Ok(decoded)) if decoded.is_empty() => {}
+                    result => {
+                        this.timers.on_ready();
+                        if let Some(Ok(decoded)) = &result {
+                            this.buf.record_item(decoded);
+                            if let Some(meter) = this.meter {
+                                meter.record(decoded.len());
+                            }
+                            if let Err(err) = this.limit.record(decoded) {
+                                *this.closed = true;
+                                return Poll::Ready(Some(Err(err)));
+                            }
+                        }
+                        return Poll::Ready(result.map(|r| r.map(shrink)));
+                    }
+                }
+            }
+        }
+        let result = ready!(self.as_mut().poll_decode(cx));
+        let buf = self.buffer_mut();
+        Poll::Ready(result.map(|r| r.map(|valid| shrink(buf.split_decoded(valid)))))
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<R> std::async_iter::AsyncIterator for Utf8Decoder<R>
+where
+    R: AsyncRead,
+{
+    type Item = Result<String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Stream::poll_next(self, cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        Stream::size_hint(self)
+    }
+}
+
+/// Error of a reader which reported more bytes than the slice it was given
+fn overread(n: usize, len: usize) -> DecodeError {
+    let msg = format!("reader reported {} bytes read into {} bytes", n, len);
+    futures_io::Error::new(futures_io::ErrorKind::InvalidData, msg).into()
+}
+
+pub(crate) fn check_capacity(capacity: usize) -> Result<()> {
+    if capacity < MINIMUM_BUF_SIZE {
+        return Err(DecodeError::CapacityTooSmall(capacity));
+    }
+    Ok(())
+}
+
+/// Drop the incomplete sequence in `buf` which is never going to be completed,
+/// and return the item to yield instead according to `policy`.
+fn drop_incomplete(buf: &mut Buffer, policy: ErrorPolicy) -> Option<Result<usize>> {
+    let replacement = policy.replacement();
+    buf.record_invalid(1, replacement.unwrap_or_default());
+    let remains = buf.filled().to_vec();
+    let offset = buf.offset();
+    if let Some(replacement) = replacement {
+        buf.warn(offset, &remains, replacement);
+    }
+    buf.splice(0, replacement.unwrap_or_default());
+    match replacement {
+        None => Some(Err(DecodeError::incomplete_at(remains, offset))),
+        Some(replacement) if !replacement.is_empty() => Some(Ok(replacement.len())),
+        Some(_) => None,
+    }
+}
+
+/// Read once directly into `out` and return its valid prefix as String.
+///
+/// An incomplete sequence at the end is moved to `buf`, which must be empty.
+fn decode_direct<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    buf: &mut Buffer,
+    out: &mut Vec<u8>,
+    policy: ErrorPolicy,
+) -> Poll<Option<Result<String>>>
+where
+    R: AsyncRead,
+{
+    let window = buf.window();
+    if out.len() < window {
+        out.resize(window, 0);
+    }
+    let n = loop {
+        match ready!(reader.as_mut().poll_read(cx, &mut out[..window])) {
+            Err(err) if buf.retry_interrupted(&err) => continue,
+            Err(err) if buf.is_pending(&err) => {
+                // The reader has not registered the task to be woken
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            result => break result?,
+        }
+    };
+    // The upstream is closed
+    if n == 0 {
+        if buf.retry_zero_read() {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        buf.record_eof();
+        return Poll::Ready(None);
+    }
+    if n > window {
+        return Poll::Ready(Some(Err(overread(n, window))));
+    }
+    if n == window {
+        buf.grow_window();
+    }
+    buf.tune(n, n >= buf.capacity());
+    let valid = match step(&out[..n], policy) {
+        Step::Valid(valid) => {
+            let remains = &out[valid..n];
+            buf.unfilled_mut()[..remains.len()].copy_from_slice(remains);
+            buf.advance(remains.len());
+            valid
+        }
+        Step::Replace { replacement, .. } => {
+            let mut decoded = Vec::with_capacity(n);
+            let (consumed, invalid) = replace_invalid(&out[..n], replacement, &mut decoded);
+            let base = buf.offset() - n as u64;
+            for range in &invalid {
+                buf.warn(base + range.start as u64, &out[range.clone()], replacement);
+            }
+            buf.record_invalid(invalid.len(), replacement);
+            let remains = &out[consumed..n];
+            buf.unfilled_mut()[..remains.len()].copy_from_slice(remains);
+            buf.advance(remains.len());
+            return Poll::Ready(Some(Ok(validated::into_string(decoded))));
+        }
+        Step::Fail { .. } => {
+            buf.record_invalid(1, b"");
+            let base = buf.offset() - n as u64;
+            let err = DecodeError::invalid_dropping(&out[..n], base);
+            return Poll::Ready(Some(Err(err)));
+        }
+    };
+    if valid == 0 {
+        return Poll::Ready(Some(Ok(String::new())));
+    }
+    let mut decoded = std::mem::take(out);
+    decoded.truncate(valid);
+    Poll::Ready(Some(Ok(validated::into_string(decoded))))
+}
+
+/// Read once into `buf` and return the length of the valid prefix of the filled
+/// bytes, where the first `valid` bytes are known to be valid.
+fn decode_next<R>(
+    mut reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    buf: &mut Buffer,
+    valid: usize,
+    policy: ErrorPolicy,
+) -> Poll<Option<Result<usize>>>
+where
+    R: AsyncRead,
+{
+    let len = buf.unfilled_mut().len();
+    // Reading into an empty slice would be taken for the end of the stream
+    if len == 0 {
+        return Poll::Ready(Some(Err(DecodeError::BufferFull)));
+    }
+    let n = loop {
+        match ready!(reader.as_mut().poll_read(cx, buf.unfilled_mut())) {
+            Err(err) if buf.retry_interrupted(&err) => continue,
+            Err(err) if buf.is_pending(&err) => {
+                // The reader has not registered the task to be woken
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            result => break result?,
+        }
+    };
+    // The upstream is closed
+    if n == 0 {
+        if buf.retry_zero_read() {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        buf.record_eof();
+        return Poll::Ready(None);
+    }
+    if n > len {
+        return Poll::Ready(Some(Err(overread(n, len))));
+    }
+    buf.advance(n);
+    let full = buf.is_full();
+    buf.tune(n, full);
+    Poll::Ready(Some(decode_filled(buf, valid, policy)))
+}
+
+/// Return the length of the valid prefix of the filled bytes, where the first
+/// `valid` bytes are known to be valid. Invalid input drops the bytes after
+/// them unless the policy replaces it.
+fn decode_filled(buf: &mut Buffer, valid: usize, policy: ErrorPolicy) -> Result<usize> {
+    let filled = &buf.filled()[valid..];
+    match step(filled, policy) {
+        // An incomplete sequence at the end is what this decoder exists for
+        Step::Valid(n) => Ok(valid + n),
+        Step::Replace { replacement, .. } => Ok(buf.replace_invalid(valid, replacement)),
+        Step::Fail { .. } => {
+            // An unexpected byte was encounted. While this decoder is not
+            // lossy decoding, return the error itself and stop decoding.
+            // The bytes which have not been yielded are carried by the error.
+            let err = DecodeError::invalid_dropping(filled, buf.offset() + valid as u64);
+            // Drop the bytes so that decoding can go on
+            buf.truncate(valid);
+            buf.record_invalid(1, b"");
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::channel::mpsc;
+    use futures::io;
+    use futures::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    async fn timeout<T>(future: impl Future<Output = T> + Unpin) -> Result<T> {
+        let result =
+            async_std::future::timeout(std::time::Duration::from_millis(100), future).await?;
+        Ok(result)
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_demo() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        tx.send(Ok(vec![240])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![159])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![146])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![150])).await?;
+        assert_eq!("💖", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_background() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        let consumer = async_std::task::spawn(async move { decoder.next().await });
+        tx.send(Ok(vec![240])).await?;
+        tx.send(Ok(vec![159])).await?;
+        tx.send(Ok(vec![146])).await?;
+        tx.send(Ok(vec![150])).await?;
+        assert_eq!("💖", timeout(consumer).await?.unwrap()?);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_1byte_character() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        tx.send(Ok(vec![0x24])).await?;
+        assert_eq!("\u{0024}", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_2byte_character() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        // Complete
+        tx.send(Ok(vec![0xC2, 0xA2])).await?;
+        assert_eq!("\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        // Incremental
+        tx.send(Ok(vec![0xC2])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![0xA2])).await?;
+        assert_eq!("\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_3byte_character() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        // Complete
+        tx.send(Ok(vec![0xE0, 0xA4, 0xB9])).await?;
+        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        // Incremental
+        tx.send(Ok(vec![0xE0])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![0xA4])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![0xB9])).await?;
+        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_4byte_character() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        // Complete
+        tx.send(Ok(vec![0xF0, 0x90, 0x8D, 0x88])).await?;
+        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        // Incremental
+        tx.send(Ok(vec![0xF0])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![0x90])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![0x8D])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![0x88])).await?;
+        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_ok() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        tx.send(Ok(vec![
+            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
+        ]))
+        .await?;
+        tx.send(Ok(vec![
+            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
+        ]))
+        .await?;
+        tx.send(Ok(vec![
+            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
+        ]))
+        .await?;
+        assert_eq!(
+            "\u{0024}\u{00A2}\u{0939}\u{10348}",
+            timeout(decoder.next()).await?.unwrap()?
+        );
+        assert_eq!(
+            "\u{0024}\u{00A2}\u{0939}\u{10348}",
+            timeout(decoder.next()).await?.unwrap()?
+        );
+        assert_eq!(
+            "\u{0024}\u{00A2}\u{0939}\u{10348}",
+            timeout(decoder.next()).await?.unwrap()?
+        );
+        assert!(timeout(decoder.next()).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_ok_with_minimum_capacity() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::with_capacity(MINIMUM_BUF_SIZE, rx.into_async_read());
+
+        // Complete
+        tx.send(Ok(vec![
+            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
+        ]))
+        .await?;
+        tx.send(Ok(vec![
+            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
+        ]))
+        .await?;
+        tx.send(Ok(vec![
+            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
+        ]))
+        .await?;
+        assert_eq!("\u{0024}\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!("\u{0024}\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!("\u{0024}\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_large_read_with_remains() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::with_capacity(8, rx.into_async_read());
+
+        tx.send(Ok(vec![0x24, 0x24, 0x24, 0x24, 0x24, 0xF0, 0x9F, 0x92]))
+            .await?;
+        assert_eq!("$$$$$", timeout(decoder.next()).await?.unwrap()?);
+        tx.send(Ok(vec![0x96, 0x24])).await?;
+        assert_eq!("💖$", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await.is_err());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_invalid() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        tx.send(Ok(vec![0x24, 0xFF])).await?;
+        assert!(matches!(
+            timeout(decoder.next()).await?.unwrap(),
+            Err(DecodeError::Utf8Error { .. })
+        ));
+        tx.send(Ok(vec![0x24])).await?;
+        assert_eq!("\u{0024}", timeout(decoder.next()).await?.unwrap()?);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_next_str() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        tx.send(Ok(vec![0x24, 0xF0, 0x9F])).await?;
+        assert_eq!("\u{0024}", decoder.next_str().await.unwrap()?);
+        tx.send(Ok(vec![0x92, 0x96])).await?;
+        assert_eq!("💖", decoder.next_str().await.unwrap()?);
+        tx.send(Ok(vec![0x24])).await?;
+        assert_eq!("\u{0024}", timeout(decoder.next()).await?.unwrap()?);
+        drop(tx);
+        assert!(decoder.next_str().await.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_next_into() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+        let mut out = String::new();
+
+        tx.send(Ok(vec![0x24, 0xF0, 0x9F])).await?;
+        assert_eq!(1, decoder.next_into(&mut out).await?);
+        tx.send(Ok(vec![0x92, 0x96])).await?;
+        assert_eq!(4, decoder.next_into(&mut out).await?);
+        drop(tx);
+        assert_eq!(0, decoder.next_into(&mut out).await?);
+        assert_eq!("\u{0024}💖", out);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_direct_read() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+        decoder.set_direct_read(true);
+
+        tx.send(Ok(vec![0x24, 0x24])).await?;
+        assert_eq!("$$", timeout(decoder.next()).await?.unwrap()?);
+        tx.send(Ok(vec![0x24, 0xF0, 0x9F])).await?;
+        assert_eq!("$", timeout(decoder.next()).await?.unwrap()?);
+        tx.send(Ok(vec![0x92])).await?;
+        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![0x96, 0x24])).await?;
+        assert_eq!("💖$", timeout(decoder.next()).await?.unwrap()?);
+        tx.send(Ok(vec![0xFF])).await?;
+        assert!(timeout(decoder.next()).await?.unwrap().is_err());
+        drop(tx);
+        assert!(timeout(decoder.next()).await?.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_eager_drain() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+        decoder.set_eager_drain(true);
+
+        tx.send(Ok(vec![0x24])).await?;
+        tx.send(Ok(vec![0xF0, 0x9F])).await?;
+        tx.send(Ok(vec![0x92, 0x96])).await?;
+        tx.send(Ok(vec![0x24, 0xFF])).await?;
+        assert_eq!("$💖", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await?.unwrap().is_err());
+        tx.send(Ok(vec![0x24])).await?;
+        tx.send(Ok(vec![0x24])).await?;
+        drop(tx);
+        assert_eq!("$$", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await?.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_eager_drain_within_budget() -> Result<()> {
+        struct AlwaysReady;
+
+        impl AsyncRead for AlwaysReady {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                buf[0] = 0x24;
+                Poll::Ready(Ok(1))
+            }
+        }
+
+        let mut decoder = Utf8Decoder::new(AlwaysReady);
+        decoder.set_eager_drain(true);
+        assert_eq!(READ_BUDGET, timeout(decoder.next()).await?.unwrap()?.len());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_read_strategy() -> Result<()> {
+        let chunks = || {
+            (0..8)
+                .map(|_| Ok(vec![0x24]))
+                .collect::<Vec<io::Result<Vec<u8>>>>()
+        };
+
+        let mut decoder = Utf8Decoder::new(stream::iter(chunks()).into_async_read());
+        decoder.set_read_strategy(ReadStrategy::Budget(3));
+        let decoded = decoder.try_collect::<Vec<_>>().await?;
+        assert_eq!(vec!["$$$", "$$$", "$$"], decoded);
+
+        let mut decoder = Utf8Decoder::new(stream::iter(chunks()).into_async_read());
+        decoder.set_read_strategy(ReadStrategy::UntilPending);
+        let decoded = decoder.try_collect::<Vec<_>>().await?;
+        assert_eq!(vec!["$$$$$$$$"], decoded);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_set_capacity_keeps_remains() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::with_capacity(16, rx.into_async_read());
+
+        tx.send(Ok(vec![0x24, 0xF0, 0x9F])).await?;
+        assert_eq!("$", timeout(decoder.next()).await?.unwrap()?);
+        decoder.set_capacity(1);
+        assert_eq!(MINIMUM_BUF_SIZE, decoder.capacity());
+        tx.send(Ok(vec![0x92, 0x96, 0x24, 0x24, 0x24])).await?;
+        assert_eq!("💖", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!("$$$", timeout(decoder.next()).await?.unwrap()?);
+        decoder.reserve(4);
+        assert_eq!(MINIMUM_BUF_SIZE + 4, decoder.capacity());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_autotune() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24; 1024])];
+        let mut decoder = Utf8Decoder::with_capacity(8, stream::iter(chunks).into_async_read());
+        decoder.set_autotune(Some(Autotune { min: 1, max: 64 }));
+
+        let mut decoded = String::new();
+        while let Some(s) = decoder.next().await {
+            decoded.push_str(&s?);
+        }
+        assert_eq!("$".repeat(1024), decoded);
+        assert_eq!(64, decoder.capacity());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_reset() -> Result<()> {
+        let chunks = |bytes: Vec<u8>| {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(bytes)];
+            stream::iter(chunks).into_async_read()
+        };
+        let mut decoder = Utf8Decoder::with_capacity(16, chunks(vec![0x24, 0xF0, 0x9F]));
+        decoder.set_eager_drain(true);
+        assert_eq!("$", timeout(decoder.next()).await?.unwrap()?);
+
+        // The pending partial sequence belongs to the old reader
+        let _ = decoder.reset(chunks(vec![0x24, 0x24]));
+        assert_eq!(0, decoder.buffer_len());
+        assert_eq!(ReadStrategy::Budget(READ_BUDGET), decoder.strategy);
+        assert_eq!("$$", timeout(decoder.next()).await?.unwrap()?);
+        assert!(timeout(decoder.next()).await?.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_set_reader() -> Result<()> {
+        let chunks = |bytes: Vec<u8>| {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(bytes)];
+            stream::iter(chunks).into_async_read()
+        };
+        let mut decoder = Utf8Decoder::with_capacity(16, chunks(vec![0x24, 0xF0, 0x9F]));
+        assert_eq!("$", timeout(decoder.next()).await?.unwrap()?);
+
+        // The pending partial sequence is completed by the new reader
+        let _ = decoder.set_reader(chunks(vec![0x92, 0x96]));
+        assert_eq!(2, decoder.buffer_len());
+        assert_eq!("💖", timeout(decoder.next()).await?.unwrap()?);
+
+        // The reader type may change as well
+        let mut decoder = Utf8Decoder::with_capacity(16, chunks(vec![0x24, 0xF0, 0x9F]));
+        assert_eq!("$", timeout(decoder.next()).await?.unwrap()?);
+        let decoder = decoder.map_reader(|_| io::Cursor::new(vec![0x92, 0x96]));
+        let decoded = decoder.try_collect::<Vec<_>>().await?;
+        assert_eq!(vec!["💖"], decoded);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_close() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+        decoder.set_direct_read(true);
+
+        tx.send(Ok(vec![0x24, 0xE2, 0x82])).await?;
+        assert_eq!("$", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!(vec![0xE2, 0x82], decoder.close());
+        assert!(decoder.is_closed());
+
+        // Bytes after the close are left to the reader
+        tx.send(Ok(vec![0x24])).await?;
+        assert!(timeout(decoder.next()).await?.is_none());
+        let mut rest = Vec::new();
+        drop(tx);
+        decoder.get_mut().read_to_end(&mut rest).await?;
+        assert_eq!(vec![0x24], rest);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_on_drop_remainder() -> Result<()> {
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let on_drop = {
+            let dropped = dropped.clone();
+            move |remainder: &[u8]| dropped.lock().unwrap().extend_from_slice(remainder)
+        };
+
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24, 0xE2, 0x82])];
+        let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+        decoder.on_drop_remainder(on_drop.clone());
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        drop(decoder);
+        assert_eq!(vec![0xE2, 0x82], *dropped.lock().unwrap());
+
+        // Nothing is lost through 'into_parts'
+        dropped.lock().unwrap().clear();
+        let mut decoder = Utf8Decoder::with_initial_bytes(&[0xE2], io::empty());
+        decoder.on_drop_remainder(on_drop);
+        let (_, remainder) = decoder.into_parts();
+        assert_eq!(vec![0xE2], remainder);
+        assert!(dropped.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn decoder_debug() {
+        let decoder = Utf8Decoder::with_initial_bytes(&[0xE2, 0x82], io::empty());
+        let debug = format!("{:?}", decoder);
+        assert!(debug.starts_with("Utf8Decoder { reader: Empty"));
+        assert!(debug.contains(r#"buffer_len: 2, pending: ["0xE2", "0x82"]"#));
+
+        let decoder = Utf8Decoder::with_initial_bytes(&[0x24; 10], io::empty());
+        let debug = format!("{:?}", decoder);
+        assert!(debug.contains(r#""0x24", ... 2 more]"#));
+    }
+
+    #[async_std::test]
+    async fn decoder_stats() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![
+            Ok(vec![0x24, 0xFF, 0x24, 0xFE]),
+            Ok(vec![0xE2, 0x82]),
+            Ok(vec![0xAC, 0xF0, 0x9F]),
+        ];
+        let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+        decoder.set_error_policy(ErrorPolicy::Replace);
+        let decoded = decoder.by_ref().try_collect::<Vec<_>>().await?;
+        assert_eq!("$\u{FFFD}$\u{FFFD}€\u{FFFD}", decoded.concat());
+        assert_eq!(
+            DecoderStats {
+                bytes_read: 9,
+                reads: 3,
+                items: decoded.len() as u64,
+                invalid_sequences: 3,
+                replacements: 3,
+            },
+            decoder.stats()
+        );
+
+        let _ = decoder.reset(stream::iter(vec![]).into_async_read());
+        assert_eq!(DecoderStats::default(), decoder.stats());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_peek() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24, 0xE2]), Ok(vec![0x82, 0xAC])];
+        let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+
+        assert!(matches!(decoder.peek().await, Some(Ok("$"))));
+        assert!(matches!(decoder.peek().await, Some(Ok("$"))));
+        assert_eq!("$", decoder.next_str().await.unwrap()?);
+        assert!(matches!(decoder.peek().await, Some(Ok("€"))));
+        assert_eq!(vec!["€"], decoder.by_ref().try_collect::<Vec<_>>().await?);
+        assert!(decoder.peek().await.is_none());
+
+        // Errors are held back as well
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0xFF]), Ok(vec![0x24])];
+        let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+        assert!(matches!(
+            decoder.peek().await,
+            Some(Err(DecodeError::Utf8Error { .. }))
+        ));
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::Utf8Error { .. }))
+        ));
+        assert_eq!("$", decoder.next().await.unwrap()?);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_lend_as_stream() -> Result<()> {
+        async fn first<S>(mut stream: S) -> Option<Result<String>>
+        where
+            S: Stream<Item = super::Result<String>> + Unpin,
+        {
+            Some(stream.next().await?.map_err(Into::into))
+        }
+
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24]), Ok(vec![0x25])];
+        let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+        assert_eq!("$", first(&mut decoder).await.unwrap()?);
+        assert_eq!("%", decoder.next().await.unwrap()?);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_new_boxed() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0xE2, 0x82]), Ok(vec![0xAC])];
+        let decoders = vec![
+            BoxUtf8Decoder::new_boxed(io::Cursor::new(vec![0x24])),
+            BoxUtf8Decoder::new_boxed(stream::iter(chunks).into_async_read()),
+        ];
+        let decoded = stream::select_all(decoders).try_collect::<Vec<_>>().await?;
+        assert_eq!(vec!["$", "€"], decoded);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_boxed() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0xE2, 0x82]), Ok(vec![0xAC])];
+        let decoders = vec![
+            Utf8Decoder::new(&b"$"[..]).boxed(),
+            Utf8Decoder::new(stream::iter(chunks).into_async_read()).boxed(),
+        ];
+        let decoded = stream::iter(decoders)
+            .flatten()
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec!["$", "€"], decoded);
+
+        // A reader which is not Send
+        let reader = io::Cursor::new(std::rc::Rc::<[u8]>::from(&b"$"[..]));
+        let decoded = Utf8Decoder::new(reader)
+            .boxed_local()
+            .try_collect::<String>()
+            .await?;
+        assert_eq!("$", decoded);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "unstable")]
+    #[async_std::test]
+    async fn decoder_async_iterator() -> Result<()> {
+        use std::async_iter::AsyncIterator;
+
+        let mut decoder = std::pin::pin!(Utf8Decoder::new(&[0x24, 0xE2, 0x82, 0xAC][..]));
+        let decoded = poll_fn(|cx| AsyncIterator::poll_next(decoder.as_mut(), cx)).await;
+        assert_eq!("$€", decoded.unwrap()?);
+        assert!(poll_fn(|cx| AsyncIterator::poll_next(decoder.as_mut(), cx))
+            .await
+            .is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_poll_next_chunk() -> Result<()> {
+        struct Concat<R> {
+            decoder: Utf8Decoder<R>,
+            out: String,
+        }
+
+        impl<R: AsyncRead + Unpin> Future for Concat<R> {
+            type Output = super::Result<String>;
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                while let Some(decoded) = ready!(self.decoder.poll_next_chunk(cx)) {
+                    self.out.push_str(&decoded?);
+                }
+                Poll::Ready(Ok(std::mem::take(&mut self.out)))
+            }
+        }
+
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24, 0xE2]), Ok(vec![0x82, 0xAC])];
+        let decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+        let out = String::new();
+        assert_eq!("$€", Concat { decoder, out }.await?);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_large_reads() -> Result<()> {
+        let text = "$ü€💖".repeat(LARGE_READ_SIZE / 5);
+        let mut decoder = Utf8Decoder::new(io::Cursor::new(text.clone()));
+        decoder.enable_large_reads();
+        assert_eq!(LARGE_READ_SIZE, decoder.capacity());
+
+        let decoded = decoder.try_collect::<Vec<_>>().await?;
+        assert_eq!(text, decoded.concat());
+        assert_eq!(2, decoded.len());
+        assert!(decoded[0].len() > LARGE_READ_SIZE - MINIMUM_BUF_SIZE);
+
+        Ok(())
+    }
+
+    #[test]
+    fn decoder_default_capacity() {
+        // Other tests running in parallel are fine with this capacity
+        set_default_capacity(1024);
+        assert_eq!(1024, default_capacity());
+        assert_eq!(1024, Utf8Decoder::new(io::empty()).capacity());
+        set_default_capacity(DEFAULT_BUF_SIZE);
+        assert_eq!(DEFAULT_BUF_SIZE, Utf8Decoder::new(io::empty()).capacity());
+    }
+
+    #[async_std::test]
+    async fn decoder_error_policy() -> Result<()> {
+        let chunks = || {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![
+                Ok(vec![0x24, 0xFF, 0xF0, 0x9F]),
+                Ok(vec![0x92, 0x96, 0x80, 0x24, 0xF0]),
+            ];
+            stream::iter(chunks).into_async_read()
+        };
+        for direct in [false, true] {
+            let mut decoder = Utf8Decoder::new(chunks());
+            decoder.set_direct_read(direct);
+            decoder.set_error_policy(ErrorPolicy::Replace);
+            let decoded = decoder.try_collect::<String>().await?;
+            assert_eq!("$\u{FFFD}💖\u{FFFD}$\u{FFFD}", decoded);
+
+            let mut decoder = Utf8Decoder::new(chunks());
+            decoder.set_direct_read(direct);
+            decoder.set_error_policy(ErrorPolicy::Skip);
+            let decoded = decoder.try_collect::<String>().await?;
+            assert_eq!("$💖$", decoded);
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_error_not_repeated() -> Result<()> {
+        for direct in [false, true] {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24, 0xFF, 0x24]), Ok(vec![0x41])];
+            let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+            decoder.set_direct_read(direct);
+            assert!(matches!(
+                decoder.next().await,
+                Some(Err(DecodeError::Utf8Error { offset: 1, .. }))
+            ));
+            // The read the error is found in has been dropped
+            assert_eq!("A", decoder.next().await.unwrap()?);
+            assert!(decoder.next().await.is_none());
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_decode_not_unpin() -> Result<()> {
+        pin_project! {
+            struct NotUnpin<R> {
+                #[pin]
+                reader: R,
+                #[pin]
+                _pinned: std::marker::PhantomPinned,
+            }
+        }
+
+        impl<R: AsyncRead> AsyncRead for NotUnpin<R> {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                self.project().reader.poll_read(cx, buf)
+            }
+        }
+
+        let reader = NotUnpin {
+            reader: io::Cursor::new(vec![0x24, 0xF0, 0x9F, 0x92, 0x96]),
+            _pinned: std::marker::PhantomPinned,
+        };
+        let decoder = std::pin::pin!(Utf8Decoder::new(reader));
+        assert_eq!("$💖", decoder.try_collect::<String>().await?);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_defined_errors_on_broken_invariants() -> Result<()> {
+        struct Overread;
+
+        impl AsyncRead for Overread {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                Poll::Ready(Ok(buf.len() + 1))
+            }
+        }
+
+        let mut decoder = Utf8Decoder::new(Overread);
+        let err = decoder.next().await.unwrap().unwrap_err();
+        assert!(matches!(err, DecodeError::IOError(e) if e.kind() == io::ErrorKind::InvalidData));
+
+        let mut buf = Buffer::with_capacity(MINIMUM_BUF_SIZE);
+        buf.splice(0, &[0xF0, 0x9F, 0x92, 0x96]);
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut reader = Overread;
+        let result = decode_next(
+            Pin::new(&mut reader),
+            &mut cx,
+            &mut buf,
+            0,
+            ErrorPolicy::Strict,
         );
-        let buffer = vec![0; capacity];
-        Self {
-            reader,
-            buf: buffer.into_boxed_slice(),
-            remains: 0,
-        }
+        assert!(matches!(
+            result,
+            Poll::Ready(Some(Err(DecodeError::BufferFull)))
+        ));
+
+        Ok(())
     }
 
-    /// Consumes this decoder, returning the underlying reader.
-    pub fn into_inner(self) -> R {
-        self.reader
+    #[async_std::test]
+    async fn decoder_into_parts() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        tx.send(Ok(vec![0x24, 0xF0, 0x9F])).await?;
+        assert_eq!("$", timeout(decoder.next()).await?.unwrap()?);
+        let (reader, remainder) = decoder.into_parts();
+        assert_eq!(vec![0xF0, 0x9F], remainder);
+
+        let mut decoder = Utf8Decoder::from_parts(reader, remainder);
+        tx.send(Ok(vec![0x92, 0x96])).await?;
+        assert_eq!("💖", timeout(decoder.next()).await?.unwrap()?);
+
+        // Complete text in the remainder is decoded without waiting for a read
+        let mut decoder = Utf8Decoder::from_parts(decoder.into_inner(), b"$$".to_vec());
+        assert_eq!("$$", timeout(decoder.next()).await?.unwrap()?);
+        drop(tx);
+        assert!(timeout(decoder.next()).await?.is_none());
+
+        Ok(())
     }
 
-    /// Acquires a reference to the underlying reader that this
-    /// decoder is pulling from.
-    pub fn get_ref(&self) -> &R {
-        &self.reader
+    #[async_std::test]
+    async fn decoder_with_initial_bytes() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x92, 0x96, 0x24])];
+        let reader = stream::iter(chunks).into_async_read();
+        let decoder = Utf8Decoder::with_initial_bytes(&[0x24, 0xF0, 0x9F], reader);
+
+        assert_eq!("$💖$", decoder.try_collect::<String>().await?);
+
+        Ok(())
     }
 
-    /// Acquires a mutable reference to the underlying reader that
-    /// this decoder is pulling from.
-    pub fn get_mut(&mut self) -> &mut R {
-        &mut self.reader
+    #[async_std::test]
+    async fn decoder_take_remainder() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+
+        tx.send(Ok(vec![0x24, 0xF0, 0x9F])).await?;
+        assert_eq!("$", decoder.next_str().await.unwrap()?);
+        assert_eq!(&[0xF0, 0x9F], decoder.pending_bytes());
+        assert_eq!(vec![0xF0, 0x9F], decoder.take_remainder());
+        assert!(decoder.pending_bytes().is_empty());
+
+        tx.send(Ok(vec![0x24])).await?;
+        assert_eq!("$", timeout(decoder.next()).await?.unwrap()?);
+
+        Ok(())
     }
-}
 
-impl<R> Stream for Utf8Decoder<R>
-where
-    R: AsyncRead + Unpin,
-{
-    type Item = Result<String>;
+    #[test]
+    fn decoder_try_with_capacity() {
+        assert!(Utf8Decoder::try_with_capacity(MINIMUM_BUF_SIZE, io::empty()).is_ok());
+        assert!(matches!(
+            Utf8Decoder::try_with_capacity(1, io::empty()),
+            Err(DecodeError::CapacityTooSmall(1))
+        ));
+    }
 
-    fn poll_next(
-        self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> Poll<Option<<Self as Stream>::Item>> {
-        let mut this = self.project();
-        let buf = this.buf;
-        loop {
-            let remains = *this.remains;
-            let reader = this.reader.as_mut();
-            match ready!(decode_next(reader, cx, buf, remains)) {
-                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
-                Some(Ok((decoded, remains))) => {
-                    *this.remains = remains;
-                    if decoded.is_empty() {
-                        continue;
-                    }
-                    return Poll::Ready(Some(Ok(decoded)));
-                }
-                None => {
-                    if remains > 0 {
-                        let remains = buf[..remains].to_vec();
-                        let err = DecodeError::IncompleteUtf8Sequence(remains);
-                        return Poll::Ready(Some(Err(err)));
-                    }
-                    return Poll::Ready(None);
-                }
-            }
-        }
+    #[test]
+    #[should_panic(expected = "capacity must be at least 4 but 1 is specified")]
+    fn decoder_with_capacity_panics() {
+        Utf8Decoder::with_capacity(1, io::empty());
     }
-}
 
-fn decode_next<'a, R>(
-    reader: Pin<&mut R>,
-    cx: &mut Context<'_>,
-    buf: &'a mut [u8],
-    s: usize,
-) -> Poll<Option<Result<(String, usize)>>>
-where
-    R: AsyncRead,
-{
-    debug_assert!(buf.len() > s);
-    let n = ready!(reader.poll_read(cx, &mut buf[s..]))?;
-    // The upstream is closed
-    if n == 0 {
-        return Poll::Ready(None);
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn decoder_reserve_panics_on_overflow() {
+        Utf8Decoder::new(io::empty()).reserve(usize::MAX);
     }
-    let e = s + n;
-    debug_assert!(buf.len() >= e);
-    let result = match std::str::from_utf8(&buf[..e]) {
-        Ok(decoded) => Ok((decoded.to_string(), 0)),
-        Err(err) => match err.error_len() {
-            Some(_) => {
-                // An unexpected byte was encounted. While this decoder is not
-                // lossy decoding, return the error itself and stop decoding.
-                Err(err.into())
-            }
-            None => {
-                // The end of the input was reached unexpectedly. This is what
-                // this decoder exists for.
-                let (valid, after_valid) = buf.split_at(err.valid_up_to());
-                // Copy 'valid' into the Heap as String
-                let decoded = unsafe { std::str::from_utf8_unchecked(valid) };
-                let decoded = decoded.to_string();
-                // Copy 'after_valid' at the front of the 'buf'
-                let remains = e - valid.len();
-                unsafe {
-                    // +-------------------------------------------------------------+
-                    // |                            buf                              |
-                    // +----------------+--------------------------------------------+
-                    // |     valid      | after_valid                                |
-                    // +----------------+--------------------------------------------+
-                    // |////////////////|#####.......................................|
-                    // +----------------+--------------------------------------------+
-                    //                               |
-                    //                               v
-                    // +-------------------------------------------------------------+
-                    // |                            buf                              |
-                    // +----------------+--------------------------------------------+
-                    // |     valid      | after_valid                                |
-                    // +----------------+--------------------------------------------+
-                    // |#####...........|............................                |
-                    // +----------------+--------------------------------------------+
-                    //
-                    // XXX: Can we use 'copy_nonoverlapping' here?
-                    // std::ptr::copy_nonoverlapping(after_valid.as_ptr(), buf.as_mut_ptr(), remains);
-                    std::ptr::copy(after_valid.as_ptr(), buf.as_mut_ptr(), remains);
-                }
-                Ok((decoded, remains))
-            }
-        },
-    };
-    Poll::Ready(Some(result))
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use anyhow::Result;
-    use futures::channel::mpsc;
-    use futures::io;
-    use futures::prelude::*;
+    #[async_std::test]
+    async fn decoder_shrink_to_fit() -> Result<()> {
+        let chunks = || {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24; 9])];
+            stream::iter(chunks).into_async_read()
+        };
 
-    async fn timeout<T>(future: impl Future<Output = T> + Unpin) -> Result<T> {
-        let result =
-            async_std::future::timeout(std::time::Duration::from_millis(100), future).await?;
-        Ok(result)
+        let mut decoder = Utf8Decoder::with_capacity(16, chunks());
+        let decoded = timeout(decoder.next()).await?.unwrap()?;
+        assert!(decoded.capacity() > decoded.len());
+
+        let mut decoder = Utf8Decoder::with_capacity(16, chunks());
+        decoder.set_shrink_to_fit(true);
+        let decoded = timeout(decoder.next()).await?.unwrap()?;
+        assert_eq!(decoded.len(), decoded.capacity());
+
+        Ok(())
     }
 
     #[async_std::test]
-    async fn decoder_decode_demo() -> Result<()> {
+    async fn decoder_high_water_mark() -> Result<()> {
         let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
-        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+        let mut decoder = Utf8Decoder::with_capacity(16, rx.into_async_read());
 
-        tx.send(Ok(vec![240])).await?;
-        assert!(timeout(decoder.next()).await.is_err());
-        tx.send(Ok(vec![159])).await?;
-        assert!(timeout(decoder.next()).await.is_err());
-        tx.send(Ok(vec![146])).await?;
-        assert!(timeout(decoder.next()).await.is_err());
-        tx.send(Ok(vec![150])).await?;
+        tx.send(Ok(vec![0x24, 0x24, 0x24, 0xF0, 0x9F])).await?;
+        assert_eq!("$$$", timeout(decoder.next()).await?.unwrap()?);
+        assert_eq!(2, decoder.buffer_len());
+        tx.send(Ok(vec![0x92, 0x96])).await?;
         assert_eq!("💖", timeout(decoder.next()).await?.unwrap()?);
-        assert!(timeout(decoder.next()).await.is_err());
+        assert_eq!(0, decoder.buffer_len());
+        assert_eq!(5, decoder.high_water_mark());
+
+        decoder.reset_high_water_mark();
+        assert_eq!(0, decoder.high_water_mark());
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn decoder_decode_background() -> Result<()> {
-        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
-        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+    async fn decoder_read_to_string() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> =
+            vec![Ok(vec![0x24, 0xF0, 0x9F]), Ok(vec![0x92, 0x96])];
+        let decoded = decode_all(stream::iter(chunks).into_async_read()).await?;
+        assert_eq!("$💖", decoded);
 
-        let consumer = async_std::task::spawn(async move { decoder.next().await });
-        tx.send(Ok(vec![240])).await?;
-        tx.send(Ok(vec![159])).await?;
-        tx.send(Ok(vec![146])).await?;
-        tx.send(Ok(vec![150])).await?;
-        assert_eq!("💖", timeout(consumer).await?.unwrap()?);
+        let mut decoder = Utf8Decoder::new(&[0x24, 0xF0, 0x9F][..]);
+        assert!(matches!(
+            decoder.read_to_string().await,
+            Err(DecodeError::IncompleteUtf8Sequence { bytes: remains, .. }) if remains == [0xF0, 0x9F]
+        ));
+        assert_eq!("", decode_all(io::empty()).await?);
+
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24, 0xFF, 0xF0]), Ok(vec![0x9F])];
+        let (decoded, replacements) =
+            decode_to_string_lossy(stream::iter(chunks).into_async_read()).await?;
+        assert_eq!("$\u{FFFD}\u{FFFD}", decoded);
+        assert_eq!(2, replacements);
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn decoder_decode_1byte_character() -> Result<()> {
+    async fn decoder_collect_string_limited() -> Result<()> {
+        let chunks = || {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(b"abc".to_vec()), Ok(b"def".to_vec())];
+            stream::iter(chunks).into_async_read()
+        };
+        let mut decoder = Utf8Decoder::new(chunks());
+        assert_eq!("abcdef", decoder.collect_string_limited(6).await?);
+
+        let mut decoder = Utf8Decoder::new(chunks());
+        assert!(matches!(
+            decoder.collect_string_limited(5).await,
+            Err(DecodeError::LimitExceeded(Limit::Bytes(5)))
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_chain() -> Result<()> {
+        let decoder = Utf8Decoder::new(&[0x24, 0xF0, 0x9F][..])
+            .chain(&[0x92][..])
+            .chain(&[0x96, 0x24][..]);
+        let decoded = decoder.try_collect::<Vec<_>>().await?;
+        assert_eq!("$💖$", decoded.concat());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_take_bytes() -> Result<()> {
+        let mut reader = &[0x24, 0xF0, 0x9F, 0x92, 0x96, 0x24][..];
+        let decoder = Utf8Decoder::new(&mut reader).take_bytes(5);
+        assert_eq!("$💖", decoder.try_collect::<String>().await?);
+        assert_eq!([0x24], reader);
+
+        let mut decoder = Utf8Decoder::new(&[0x24, 0xF0, 0x9F, 0x92][..]).take_bytes(3);
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::IncompleteUtf8Sequence { bytes: remains, .. })) if remains == [0xF0, 0x9F]
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_max_total() -> Result<()> {
+        let chunks = || {
+            let chunks: Vec<io::Result<Vec<u8>>> =
+                vec![Ok("ab".into()), Ok("ü€".into()), Ok("c".into())];
+            stream::iter(chunks).into_async_read()
+        };
+        let mut decoder = Utf8Decoder::new(chunks());
+        decoder.set_max_total_bytes(Some(6));
+        assert_eq!("ab", decoder.next().await.unwrap()?);
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::LimitExceeded(Limit::Bytes(6))))
+        ));
+        assert!(decoder.next().await.is_none());
+
+        let mut decoder = Utf8Decoder::new(chunks());
+        decoder.set_max_total_chars(Some(4));
+        assert_eq!("ab", decoder.next().await.unwrap()?);
+        assert_eq!("ü€", decoder.next().await.unwrap()?);
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::LimitExceeded(Limit::Chars(4))))
+        ));
+
+        let mut decoder = Utf8Decoder::new(chunks());
+        decoder.set_direct_read(true);
+        decoder.set_max_total_bytes(Some(2));
+        assert_eq!("ab", decoder.next().await.unwrap()?);
+        assert!(decoder.next().await.unwrap().is_err());
+        assert!(decoder.next().await.is_none());
+
+        // The error which closes the decoder is held back by peek as well
+        let mut decoder = Utf8Decoder::new(&b"abcdef"[..]);
+        decoder.set_max_total_bytes(Some(3));
+        assert!(matches!(
+            decoder.peek().await,
+            Some(Err(DecodeError::LimitExceeded(Limit::Bytes(3))))
+        ));
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::LimitExceeded(Limit::Bytes(3))))
+        ));
+        assert!(decoder.next().await.is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "timer")]
+    #[async_std::test]
+    async fn decoder_read_timeout() -> Result<()> {
         let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
         let mut decoder = Utf8Decoder::new(rx.into_async_read());
+        decoder.set_read_timeout(Some(std::time::Duration::from_millis(10)));
 
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::Timeout(_)))
+        ));
+        // The decoder keeps working after a timeout
         tx.send(Ok(vec![0x24])).await?;
-        assert_eq!("\u{0024}", timeout(decoder.next()).await?.unwrap()?);
-        assert!(timeout(decoder.next()).await.is_err());
+        assert_eq!("$", decoder.next().await.unwrap()?);
 
         Ok(())
     }
 
+    #[cfg(feature = "timer")]
     #[async_std::test]
-    async fn decoder_decode_2byte_character() -> Result<()> {
+    async fn decoder_incomplete_timeout() -> Result<()> {
         let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
         let mut decoder = Utf8Decoder::new(rx.into_async_read());
+        decoder.set_incomplete_timeout(Some(std::time::Duration::from_millis(10)));
 
-        // Complete
-        tx.send(Ok(vec![0xC2, 0xA2])).await?;
-        assert_eq!("\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
-        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![0x24, 0xF0])).await?;
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::IncompleteUtf8Sequence { bytes: remains, .. })) if remains == [0xF0]
+        ));
+        tx.send(Ok(vec![0x9F, 0x24])).await?;
+        decoder.set_error_policy(ErrorPolicy::Replace);
+        assert_eq!("\u{FFFD}$", decoder.next().await.unwrap()?);
 
-        // Incremental
-        tx.send(Ok(vec![0xC2])).await?;
-        assert!(timeout(decoder.next()).await.is_err());
-        tx.send(Ok(vec![0xA2])).await?;
-        assert_eq!("\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
-        assert!(timeout(decoder.next()).await.is_err());
+        tx.send(Ok(vec![0xF0])).await?;
+        assert_eq!("\u{FFFD}", decoder.next().await.unwrap()?);
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn decoder_decode_3byte_character() -> Result<()> {
-        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
-        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+    async fn decoder_zero_read_policy() -> Result<()> {
+        // Reader which returns each chunk in a read, including empty ones
+        struct Transient(Vec<Vec<u8>>);
+        impl AsyncRead for Transient {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                let chunk = self.0.pop().unwrap_or_default();
+                buf[..chunk.len()].copy_from_slice(&chunk);
+                Poll::Ready(Ok(chunk.len()))
+            }
+        }
+        let reader = || Transient(vec![vec![0x24], vec![], vec![0x24]]);
 
-        // Complete
-        tx.send(Ok(vec![0xE0, 0xA4, 0xB9])).await?;
-        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
-        assert!(timeout(decoder.next()).await.is_err());
+        let decoder = Utf8Decoder::new(reader());
+        assert_eq!("$", decoder.try_collect::<String>().await?);
 
-        // Incremental
-        tx.send(Ok(vec![0xE0])).await?;
-        assert!(timeout(decoder.next()).await.is_err());
-        tx.send(Ok(vec![0xA4])).await?;
-        assert!(timeout(decoder.next()).await.is_err());
-        tx.send(Ok(vec![0xB9])).await?;
-        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
-        assert!(timeout(decoder.next()).await.is_err());
+        let mut decoder = Utf8Decoder::new(reader());
+        decoder.set_zero_read_policy(ZeroReadPolicy::Retry(1));
+        assert_eq!("$$", decoder.try_collect::<String>().await?);
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn decoder_decode_4byte_character() -> Result<()> {
-        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
-        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+    async fn decoder_interrupted_retries() -> Result<()> {
+        // Reader which is interrupted the given number of times before each read
+        struct Interrupted(usize, Vec<usize>);
+        impl AsyncRead for Interrupted {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                let this = &mut *self;
+                match this.1.last_mut() {
+                    Some(0) => {
+                        this.1.pop();
+                    }
+                    Some(n) => {
+                        *n -= 1;
+                        return Poll::Ready(Err(io::ErrorKind::Interrupted.into()));
+                    }
+                    None => return Poll::Ready(Ok(0)),
+                }
+                let n = this.0.min(buf.len());
+                buf[..n].fill(b'$');
+                Poll::Ready(Ok(n))
+            }
+        }
 
-        // Complete
-        tx.send(Ok(vec![0xF0, 0x90, 0x8D, 0x88])).await?;
-        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
-        assert!(timeout(decoder.next()).await.is_err());
+        for direct in [false, true] {
+            let mut decoder = Utf8Decoder::new(Interrupted(1, vec![3, 16]));
+            decoder.set_direct_read(direct);
+            assert_eq!("$$", decoder.try_collect::<String>().await?);
 
-        // Incremental
-        tx.send(Ok(vec![0xF0])).await?;
-        assert!(timeout(decoder.next()).await.is_err());
-        tx.send(Ok(vec![0x90])).await?;
-        assert!(timeout(decoder.next()).await.is_err());
-        tx.send(Ok(vec![0x8D])).await?;
-        assert!(timeout(decoder.next()).await.is_err());
-        tx.send(Ok(vec![0x88])).await?;
-        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
-        assert!(timeout(decoder.next()).await.is_err());
+            let mut decoder = Utf8Decoder::new(Interrupted(1, vec![0, 2]));
+            decoder.set_direct_read(direct);
+            decoder.set_interrupted_retries(1);
+            let err = decoder.next().await.unwrap().unwrap_err();
+            assert!(err.is_transient());
+            // The error does not end the stream
+            assert_eq!("$$", decoder.try_collect::<String>().await?);
+        }
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn decoder_decode_ok() -> Result<()> {
-        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
-        let mut decoder = Utf8Decoder::new(rx.into_async_read());
+    async fn decoder_would_block_as_pending() -> Result<()> {
+        // Reader which reports that it is not ready every other read
+        struct NonBlocking(bool, Vec<u8>);
+        impl AsyncRead for NonBlocking {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                let this = &mut *self;
+                this.0 = !this.0;
+                if this.0 {
+                    return Poll::Ready(Err(io::ErrorKind::WouldBlock.into()));
+                }
+                let Some(b) = this.1.pop() else {
+                    return Poll::Ready(Ok(0));
+                };
+                buf[0] = b;
+                Poll::Ready(Ok(1))
+            }
+        }
 
-        tx.send(Ok(vec![
-            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
-        ]))
-        .await?;
-        tx.send(Ok(vec![
-            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
-        ]))
-        .await?;
-        tx.send(Ok(vec![
-            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
-        ]))
-        .await?;
-        assert_eq!(
-            "\u{0024}\u{00A2}\u{0939}\u{10348}",
-            timeout(decoder.next()).await?.unwrap()?
-        );
-        assert_eq!(
-            "\u{0024}\u{00A2}\u{0939}\u{10348}",
-            timeout(decoder.next()).await?.unwrap()?
-        );
-        assert_eq!(
-            "\u{0024}\u{00A2}\u{0939}\u{10348}",
-            timeout(decoder.next()).await?.unwrap()?
-        );
-        assert!(timeout(decoder.next()).await.is_err());
+        for direct in [false, true] {
+            let mut decoder = Utf8Decoder::new(NonBlocking(false, b"cba".to_vec()));
+            decoder.set_direct_read(direct);
+            assert!(decoder.next().await.unwrap().is_err());
+
+            let mut decoder = Utf8Decoder::new(NonBlocking(false, b"cba".to_vec()));
+            decoder.set_direct_read(direct);
+            decoder.set_would_block_as_pending(true);
+            let decoded = timeout(decoder.try_collect::<String>()).await??;
+            assert_eq!("abc", decoded);
+        }
 
         Ok(())
     }
 
     #[async_std::test]
-    async fn decoder_decode_ok_with_minimum_capacity() -> Result<()> {
-        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
-        let mut decoder = Utf8Decoder::with_capacity(MINIMUM_BUF_SIZE, rx.into_async_read());
+    async fn decoder_error_offset() -> Result<()> {
+        for direct in [false, true] {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![
+                Ok(b"abc".to_vec()),
+                Ok(vec![0x24, 0xFF]),
+                Ok(vec![0x24, 0xF0]),
+            ];
+            let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+            decoder.set_direct_read(direct);
+            assert_eq!("abc", decoder.next().await.unwrap()?);
+            let err = decoder.next().await.unwrap().unwrap_err();
+            assert_eq!(Some(4), err.offset());
+            assert_eq!("$", decoder.next().await.unwrap()?);
+            let err = decoder.next().await.unwrap().unwrap_err();
+            assert_eq!(Some(6), err.offset());
+        }
 
-        // Complete
-        tx.send(Ok(vec![
-            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
-        ]))
-        .await?;
-        tx.send(Ok(vec![
-            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
-        ]))
-        .await?;
-        tx.send(Ok(vec![
-            0x24, 0xC2, 0xA2, 0xE0, 0xA4, 0xB9, 0xF0, 0x90, 0x8D, 0x88,
-        ]))
-        .await?;
-        assert_eq!("\u{0024}\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
-        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
-        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
-        assert_eq!("\u{0024}\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
-        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
-        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
-        assert_eq!("\u{0024}\u{00A2}", timeout(decoder.next()).await?.unwrap()?);
-        assert_eq!("\u{0939}", timeout(decoder.next()).await?.unwrap()?);
-        assert_eq!("\u{10348}", timeout(decoder.next()).await?.unwrap()?);
-        assert!(timeout(decoder.next()).await.is_err());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn decoder_error_carries_dropped_bytes() -> Result<()> {
+        for direct in [false, true] {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![
+                Ok(b"abc".to_vec()),
+                Ok(vec![0xE2, 0x82]),
+                Ok(vec![0xAC, 0x24, 0xFF, 0x24]),
+            ];
+            let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+            decoder.set_direct_read(direct);
+            assert_eq!("abc", decoder.next().await.unwrap()?);
+            let err = decoder.next().await.unwrap().unwrap_err();
+            assert_eq!(Some(7), err.offset());
+            assert_eq!(Some("€$"), err.as_valid_prefix());
+            assert_eq!(Some(&[0xFF, 0x24][..]), err.as_invalid_bytes());
+            assert!(decoder.next().await.is_none());
+        }
 
         Ok(())
     }
@@ -0,0 +1,166 @@
+//! Dot-stuffed multi-line response framing used by SMTP, POP3, and NNTP.
+use crate::decoder::Result;
+use crate::error::DecodeError;
+use crate::line_buffer::LineBuffer;
+use futures_core::{ready, FusedStream, Stream};
+use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Stream adapter which reads a dot-stuffed multi-line response.
+    ///
+    /// Each item is a single line without its line terminator and with a leading
+    /// stuffed dot removed. The stream ends at the terminating `.` line. When the
+    /// underlying stream ends before the terminator, an `UnexpectedEof` error is
+    /// yielded instead. Use [`DotStuffed::into_parts`] to continue with the next
+    /// response on the same stream.
+    pub struct DotStuffed<S> {
+        #[pin]
+        stream: S,
+        lines: LineBuffer,
+        // Set once the terminating line has been reached
+        complete: bool,
+        // Set once the stream has ended, with or without the terminating line
+        terminated: bool,
+    }
+}
+
+impl<S> DotStuffed<S> {
+    /// Create a new multi-line response reader from a decoded string `stream`
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            lines: LineBuffer::default(),
+            complete: false,
+            terminated: false,
+        }
+    }
+
+    /// Returns `true` once the terminating line has been reached.
+    ///
+    /// It stays `false` when the underlying stream ends before the terminator,
+    /// so a complete response can be told from a truncated one. The stream has
+    /// ended in either case once [`FusedStream::is_terminated`] returns `true`.
+    pub fn is_terminated(&self) -> bool {
+        self.complete
+    }
+
+    /// Consumes this reader, returning the underlying stream and the text which
+    /// has been received after the terminating line.
+    pub fn into_parts(mut self) -> (S, String) {
+        let rest = self.lines.take();
+        (self.stream, rest)
+    }
+
+    /// Acquires a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+impl<S> Stream for DotStuffed<S>
+where
+    S: Stream<Item = Result<String>>,
+{
+    type Item = Result<String>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let mut this = self.project();
+        if *this.terminated {
+            return Poll::Ready(None);
+        }
+        loop {
+            if let Some(mut line) = this.lines.next_line() {
+                if line == "." {
+                    *this.complete = true;
+                    *this.terminated = true;
+                    return Poll::Ready(None);
+                }
+                if line.starts_with('.') {
+                    line.remove(0);
+                }
+                return Poll::Ready(Some(Ok(line)));
+            }
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(Ok(decoded)) => this.lines.push(&decoded),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {
+                    *this.terminated = true;
+                    let err = futures_io::Error::from(futures_io::ErrorKind::UnexpectedEof);
+                    return Poll::Ready(Some(Err(DecodeError::IOError(err))));
+                }
+            }
+        }
+    }
+}
+
+impl<S> FusedStream for DotStuffed<S>
+where
+    S: Stream<Item = Result<String>>,
+{
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    fn decoder(chunks: Vec<&[u8]>) -> Utf8Decoder<impl io::AsyncRead> {
+        let chunks = chunks
+            .into_iter()
+            .map(|c| Ok(c.to_vec()))
+            .collect::<Vec<io::Result<Vec<u8>>>>();
+        Utf8Decoder::new(stream::iter(chunks).into_async_read())
+    }
+
+    #[async_std::test]
+    async fn dot_stuffed_terminator_across_reads() -> Result<()> {
+        let mut response = DotStuffed::new(decoder(vec![
+            b"Subject: \xE2\x9C",
+            b"\x89\r\n..hidden\r\n.",
+            b"\r",
+            b"\n+OK next\r\n",
+        ]));
+
+        assert_eq!("Subject: ✉", response.next().await.unwrap()?);
+        assert_eq!(".hidden", response.next().await.unwrap()?);
+        assert!(response.next().await.is_none());
+        assert!(response.is_terminated());
+
+        let (_, rest) = response.into_parts();
+        assert_eq!("+OK next\r\n", rest);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn dot_stuffed_unexpected_eof() -> Result<()> {
+        let mut response = DotStuffed::new(decoder(vec![b"line\r\n"]));
+
+        assert_eq!("line", response.next().await.unwrap()?);
+        assert!(matches!(
+            response.next().await.unwrap(),
+            Err(DecodeError::IOError(err)) if err.kind() == io::ErrorKind::UnexpectedEof
+        ));
+        assert!(response.next().await.is_none());
+        assert!(!response.is_terminated());
+        assert!(FusedStream::is_terminated(&response));
+
+        Ok(())
+    }
+}
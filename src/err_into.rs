@@ -0,0 +1,99 @@
+//! Stream of decoded text which converts errors into a user error type.
+use crate::decoder::Utf8Decoder;
+use crate::error::DecodeError;
+use futures_core::{ready, Stream};
+use futures_io::AsyncRead;
+use pin_project_lite::pin_project;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Stream created by [`Utf8Decoder::err_into`] which yields
+    /// `Result<String, E>`, converting each [`DecodeError`] with `From`.
+    ///
+    /// It saves a `map_err` at every call site when the decoder feeds code
+    /// which works with its own error type.
+    pub struct ErrInto<R, E> {
+        #[pin]
+        decoder: Utf8Decoder<R>,
+        // 'fn() -> E' keeps the stream Send and Sync regardless of 'E'
+        error: PhantomData<fn() -> E>,
+    }
+}
+
+impl<R, E> ErrInto<R, E> {
+    pub(crate) fn new(decoder: Utf8Decoder<R>) -> Self {
+        Self {
+            decoder,
+            error: PhantomData,
+        }
+    }
+
+    /// Consumes this stream, returning the underlying decoder.
+    pub fn into_inner(self) -> Utf8Decoder<R> {
+        self.decoder
+    }
+
+    /// Acquires a reference to the underlying decoder.
+    pub fn get_ref(&self) -> &Utf8Decoder<R> {
+        &self.decoder
+    }
+
+    /// Acquires a mutable reference to the underlying decoder.
+    pub fn get_mut(&mut self) -> &mut Utf8Decoder<R> {
+        &mut self.decoder
+    }
+}
+
+impl<R, E> Stream for ErrInto<R, E>
+where
+    R: AsyncRead,
+    E: From<DecodeError>,
+{
+    type Item = Result<String, E>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let item = ready!(self.project().decoder.poll_next(cx));
+        Poll::Ready(item.map(|r| r.map_err(E::from)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::prelude::*;
+
+    #[derive(Debug)]
+    enum AppError {
+        Decode(DecodeError),
+    }
+
+    impl From<DecodeError> for AppError {
+        fn from(err: DecodeError) -> Self {
+            AppError::Decode(err)
+        }
+    }
+
+    #[async_std::test]
+    async fn err_into_converts_errors() -> Result<(), AppError> {
+        let stream = Utf8Decoder::new(&b"abc"[..]).err_into::<AppError>();
+        // The '?' operator works on the user error type directly
+        let decoded = stream.try_collect::<String>().await?;
+        assert_eq!("abc", decoded);
+
+        let mut stream = Utf8Decoder::new(&[0x24, 0xFF][..]).err_into::<AppError>();
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(AppError::Decode(DecodeError::Utf8Error {
+                offset: 1,
+                ..
+            })))
+        ));
+
+        Ok(())
+    }
+}
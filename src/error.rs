@@ -1,13 +1,749 @@
+use crate::validated;
+use crate::validation::sequence_len;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
 use thiserror::Error;
 
+pub type Result<T> = core::result::Result<T, DecodeError>;
+
+/// Error of decoding.
+///
+/// New variants may be added in minor releases, so branch on the class of an
+/// error with [`DecodeError::kind`] or the helper methods rather than matching
+/// every variant.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum DecodeError {
-    #[error("incomplete utf8 sequence `{0:?}`")]
-    IncompleteUtf8Sequence(Vec<u8>),
+    #[error(
+        "incomplete utf8 sequence at byte {offset} ({} of {expected} bytes){}",
+        bytes.len(),
+        HexDump::new(bytes, bytes.len())
+    )]
+    IncompleteUtf8Sequence {
+        /// Bytes of the sequence, which start with the lead byte
+        bytes: Vec<u8>,
+        /// Offset of the first byte of the sequence in the input
+        offset: u64,
+        /// Length of the sequence implied by the lead byte. It expected
+        /// `expected - 1` continuation bytes and received `bytes.len() - 1`.
+        expected: usize,
+    },
 
-    #[error(transparent)]
-    Utf8Error(#[from] std::str::Utf8Error),
+    #[error(
+        "invalid utf8 sequence at byte {offset}{}",
+        HexDump::new(bytes, source.error_len().unwrap_or(bytes.len()))
+    )]
+    Utf8Error {
+        /// Error of the validation, whose positions are relative to the bytes
+        /// validated at once
+        source: core::str::Utf8Error,
+        /// Offset of the invalid sequence in the input
+        offset: u64,
+        /// Valid text right before the invalid sequence which has been dropped
+        /// along with this error instead of being yielded
+        prefix: String,
+        /// Raw bytes dropped along with this error, which start with the invalid
+        /// sequence
+        bytes: Vec<u8>,
+    },
 
+    #[cfg(feature = "std")]
     #[error(transparent)]
     IOError(#[from] futures_io::Error),
+
+    #[error("capacity must be at least 4 but {0} is specified")]
+    CapacityTooSmall(usize),
+
+    #[error("buffer is full without a complete utf8 sequence to decode")]
+    BufferFull,
+
+    #[error("decoded text exceeds the limit of {0}")]
+    LimitExceeded(Limit),
+
+    #[error("read timed out after {0:?}")]
+    Timeout(core::time::Duration),
+}
+
+/// Class of a [`DecodeError`] returned by [`DecodeError::kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// See [`DecodeError::IncompleteUtf8Sequence`]
+    Incomplete,
+    /// See [`DecodeError::Utf8Error`]
+    InvalidUtf8,
+    /// I/O error of the underlying reader
+    Io,
+    /// See [`DecodeError::CapacityTooSmall`]
+    CapacityTooSmall,
+    /// See [`DecodeError::BufferFull`]
+    BufferFull,
+    /// See [`DecodeError::LimitExceeded`]
+    LimitExceeded,
+    /// See [`DecodeError::Timeout`]
+    Timeout,
+}
+
+impl DecodeError {
+    /// Error of the first invalid sequence in `bytes`, which start at `base` of
+    /// the input. Only the invalid sequence is dropped, which is skipped by the
+    /// decoder.
+    #[cfg(feature = "std")]
+    pub(crate) fn invalid_at(bytes: &[u8], base: u64) -> Self {
+        let source = core::str::from_utf8(bytes).unwrap_err();
+        let end = source.valid_up_to() + source.error_len().unwrap_or(0);
+        DecodeError::invalid(source, base, &bytes[..end])
+    }
+
+    /// Same as [`DecodeError::invalid_at`] but all of `bytes` are dropped by the
+    /// decoder, so the valid prefix and the bytes after the invalid sequence are
+    /// carried as well.
+    pub(crate) fn invalid_dropping(bytes: &[u8], base: u64) -> Self {
+        let source = core::str::from_utf8(bytes).unwrap_err();
+        DecodeError::invalid(source, base, bytes)
+    }
+
+    fn invalid(source: core::str::Utf8Error, base: u64, dropped: &[u8]) -> Self {
+        let (prefix, bytes) = dropped.split_at(source.valid_up_to().min(dropped.len()));
+        DecodeError::Utf8Error {
+            source,
+            offset: base + source.valid_up_to() as u64,
+            prefix: validated::to_str(prefix).into(),
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    /// Error of an incomplete sequence which starts at `offset` of the input
+    pub(crate) fn incomplete_at(bytes: Vec<u8>, offset: u64) -> Self {
+        let expected = bytes.first().map_or(0, |lead| sequence_len(*lead));
+        DecodeError::IncompleteUtf8Sequence {
+            bytes,
+            offset,
+            expected,
+        }
+    }
+
+    /// Returns the class of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            DecodeError::IncompleteUtf8Sequence { .. } => ErrorKind::Incomplete,
+            DecodeError::Utf8Error { .. } => ErrorKind::InvalidUtf8,
+            #[cfg(feature = "std")]
+            DecodeError::IOError(_) => ErrorKind::Io,
+            DecodeError::CapacityTooSmall(_) => ErrorKind::CapacityTooSmall,
+            DecodeError::BufferFull => ErrorKind::BufferFull,
+            DecodeError::LimitExceeded(_) => ErrorKind::LimitExceeded,
+            DecodeError::Timeout(_) => ErrorKind::Timeout,
+        }
+    }
+
+    /// Returns `true` if the input ended in the middle of a sequence.
+    pub fn is_incomplete(&self) -> bool {
+        self.kind() == ErrorKind::Incomplete
+    }
+
+    /// Returns `true` if the underlying reader failed.
+    pub fn is_io(&self) -> bool {
+        self.kind() == ErrorKind::Io
+    }
+
+    /// Returns `true` if polling the decoder again may succeed.
+    ///
+    /// It is the case for [`DecodeError::Timeout`] and I/O errors of the
+    /// kinds `Interrupted`, `WouldBlock`, and `TimedOut`. Invalid input and the
+    /// other errors are permanent.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            DecodeError::Timeout(_) => true,
+            #[cfg(feature = "std")]
+            DecodeError::IOError(err) => matches!(
+                err.kind(),
+                futures_io::ErrorKind::Interrupted
+                    | futures_io::ErrorKind::WouldBlock
+                    | futures_io::ErrorKind::TimedOut
+            ),
+            _ => false,
+        }
+    }
+
+    /// Consumes this error, returning the I/O error of the underlying reader if
+    /// it is one.
+    #[cfg(feature = "std")]
+    pub fn into_io(self) -> Option<futures_io::Error> {
+        match self {
+            DecodeError::IOError(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes which could not be decoded if this error holds them.
+    ///
+    /// Bytes following an invalid sequence are included when the decoder has
+    /// dropped them along with the error.
+    pub fn as_invalid_bytes(&self) -> Option<&[u8]> {
+        match self {
+            DecodeError::IncompleteUtf8Sequence { bytes, .. } => Some(bytes),
+            DecodeError::Utf8Error { bytes, .. } if !bytes.is_empty() => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the valid text before an invalid sequence which has been dropped
+    /// along with this error, if any.
+    ///
+    /// Together with [`DecodeError::as_invalid_bytes`] it tells every byte
+    /// which has not been yielded, so that no input silently disappears.
+    pub fn as_valid_prefix(&self) -> Option<&str> {
+        match self {
+            DecodeError::Utf8Error { prefix, .. } if !prefix.is_empty() => Some(prefix),
+            _ => None,
+        }
+    }
+
+    /// Returns the offset in the input of the sequence which could not be
+    /// decoded.
+    ///
+    /// Decoders count the offset from the first byte they have been given.
+    /// Bytes handed over when a decoder is created, such as the initial bytes
+    /// of [`Utf8Decoder`], are not counted.
+    ///
+    /// [`Utf8Decoder`]: crate::Utf8Decoder
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            DecodeError::IncompleteUtf8Sequence { offset, .. } => Some(*offset),
+            DecodeError::Utf8Error { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
+/// The offset is the position in the bytes the error was found in. The bytes
+/// themselves are unknown so none are carried.
+impl From<core::str::Utf8Error> for DecodeError {
+    fn from(source: core::str::Utf8Error) -> Self {
+        DecodeError::invalid(source, 0, &[])
+    }
+}
+
+/// I/O errors of the reader are passed through, a timeout is reported as
+/// [`TimedOut`] and the other errors as [`InvalidData`] which wraps the error.
+///
+/// [`TimedOut`]: futures_io::ErrorKind::TimedOut
+/// [`InvalidData`]: futures_io::ErrorKind::InvalidData
+#[cfg(feature = "std")]
+impl From<DecodeError> for futures_io::Error {
+    fn from(err: DecodeError) -> Self {
+        let kind = match err {
+            DecodeError::IOError(err) => return err,
+            DecodeError::Timeout(_) => futures_io::ErrorKind::TimedOut,
+            _ => futures_io::ErrorKind::InvalidData,
+        };
+        futures_io::Error::new(kind, err)
+    }
+}
+
+/// An error is serialized into a structured form of its `kind`, `message`,
+/// `offset` and the `bytes` it carries in hex, so that it can be written to
+/// structured logs as it is. Fields which do not apply are `None`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DecodeError {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let bytes = self.as_invalid_bytes().map(|bytes| {
+            bytes
+                .iter()
+                .map(|b| alloc::format!("{:02x}", b))
+                .collect::<String>()
+        });
+        let mut state = serializer.serialize_struct("DecodeError", 4)?;
+        state.serialize_field("kind", &self.kind())?;
+        state.serialize_field("message", &alloc::string::ToString::to_string(self))?;
+        state.serialize_field("offset", &self.offset())?;
+        state.serialize_field("bytes", &bytes)?;
+        state.end()
+    }
+}
+
+/// The message is the same as the `Display` of the error, where the bytes are
+/// dumped in hex.
+#[cfg(feature = "defmt")]
+impl defmt::Format for DecodeError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            DecodeError::IncompleteUtf8Sequence {
+                bytes,
+                offset,
+                expected,
+            } => defmt::write!(
+                f,
+                "incomplete utf8 sequence at byte {=u64} ({=usize} of {=usize} bytes): {=[u8]:02X}",
+                offset,
+                bytes.len(),
+                expected,
+                &bytes[..],
+            ),
+            DecodeError::Utf8Error { offset, bytes, .. } => defmt::write!(
+                f,
+                "invalid utf8 sequence at byte {=u64}: {=[u8]:02X}",
+                offset,
+                &bytes[..],
+            ),
+            #[cfg(feature = "std")]
+            DecodeError::IOError(err) => defmt::write!(f, "{}", defmt::Display2Format(err)),
+            DecodeError::CapacityTooSmall(n) => defmt::write!(
+                f,
+                "capacity must be at least 4 but {=usize} is specified",
+                n
+            ),
+            DecodeError::BufferFull => defmt::write!(
+                f,
+                "buffer is full without a complete utf8 sequence to decode"
+            ),
+            DecodeError::LimitExceeded(limit) => {
+                defmt::write!(f, "decoded text exceeds the limit of {}", limit)
+            }
+            DecodeError::Timeout(timeout) => defmt::write!(f, "read timed out after {}", timeout),
+        }
+    }
+}
+
+/// An I/O error is cloned into a new error of the same kind and message, as
+/// the error itself cannot be cloned. Its source is lost.
+impl Clone for DecodeError {
+    fn clone(&self) -> Self {
+        match self {
+            DecodeError::IncompleteUtf8Sequence {
+                bytes,
+                offset,
+                expected,
+            } => DecodeError::IncompleteUtf8Sequence {
+                bytes: bytes.clone(),
+                offset: *offset,
+                expected: *expected,
+            },
+            DecodeError::Utf8Error {
+                source,
+                offset,
+                prefix,
+                bytes,
+            } => DecodeError::Utf8Error {
+                source: *source,
+                offset: *offset,
+                prefix: prefix.clone(),
+                bytes: bytes.clone(),
+            },
+            #[cfg(feature = "std")]
+            DecodeError::IOError(err) => {
+                DecodeError::IOError(futures_io::Error::new(err.kind(), err.to_string()))
+            }
+            DecodeError::CapacityTooSmall(n) => DecodeError::CapacityTooSmall(*n),
+            DecodeError::BufferFull => DecodeError::BufferFull,
+            DecodeError::LimitExceeded(limit) => DecodeError::LimitExceeded(*limit),
+            DecodeError::Timeout(timeout) => DecodeError::Timeout(*timeout),
+        }
+    }
+}
+
+/// I/O errors are equal when their [`kind`] is, as the errors themselves
+/// cannot be compared.
+///
+/// [`kind`]: futures_io::Error::kind
+impl PartialEq for DecodeError {
+    fn eq(&self, other: &Self) -> bool {
+        use DecodeError::*;
+        match (self, other) {
+            (
+                IncompleteUtf8Sequence {
+                    bytes,
+                    offset,
+                    expected,
+                },
+                IncompleteUtf8Sequence {
+                    bytes: b,
+                    offset: o,
+                    expected: e,
+                },
+            ) => bytes == b && offset == o && expected == e,
+            (
+                Utf8Error {
+                    source,
+                    offset,
+                    prefix,
+                    bytes,
+                },
+                Utf8Error {
+                    source: s,
+                    offset: o,
+                    prefix: p,
+                    bytes: b,
+                },
+            ) => source == s && offset == o && prefix == p && bytes == b,
+            #[cfg(feature = "std")]
+            (IOError(a), IOError(b)) => a.kind() == b.kind(),
+            (CapacityTooSmall(a), CapacityTooSmall(b)) => a == b,
+            (BufferFull, BufferFull) => true,
+            (LimitExceeded(a), LimitExceeded(b)) => a == b,
+            (Timeout(a), Timeout(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// The valid text dropped before an invalid sequence is the source code of the
+/// diagnostic, labeled where the sequence follows it. The sequence itself is
+/// dumped in the message as it cannot be displayed as text.
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for DecodeError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let code = match self.kind() {
+            ErrorKind::Incomplete => "incomplete_utf8_sequence",
+            ErrorKind::InvalidUtf8 => "invalid_utf8_sequence",
+            ErrorKind::Io => "io",
+            ErrorKind::CapacityTooSmall => "capacity_too_small",
+            ErrorKind::BufferFull => "buffer_full",
+            ErrorKind::LimitExceeded => "limit_exceeded",
+            ErrorKind::Timeout => "timeout",
+        };
+        Some(Box::new(alloc::format!("async_utf8_decoder::{}", code)))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = match self {
+            DecodeError::IncompleteUtf8Sequence { .. } => {
+                "the input ended in the middle of a sequence"
+            }
+            DecodeError::Utf8Error { .. } => {
+                "decode with ErrorPolicy::Replace to replace invalid sequences"
+            }
+            _ => return None,
+        };
+        Some(Box::new(help))
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        match self {
+            DecodeError::Utf8Error { prefix, .. } if !prefix.is_empty() => Some(prefix),
+            _ => None,
+        }
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match self {
+            DecodeError::Utf8Error { prefix, .. } if !prefix.is_empty() => {
+                let label = miette::LabeledSpan::at_offset(prefix.len(), "invalid sequence");
+                Some(Box::new(core::iter::once(label)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Invalid or incomplete sequence reported by [`Utf8DecoderCore::feed_into`]
+/// and [`Utf8DecoderCore::finish_into`].
+///
+/// Unlike [`DecodeError`] it carries the sequence inline, so reporting it does
+/// not allocate. It converts into [`DecodeError`] with `?` where an allocator is
+/// at hand.
+///
+/// [`Utf8DecoderCore::feed_into`]: crate::Utf8DecoderCore::feed_into
+/// [`Utf8DecoderCore::finish_into`]: crate::Utf8DecoderCore::finish_into
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[error(
+    "{} utf8 sequence at byte {offset}{}",
+    if self.kind == ErrorKind::Incomplete { "incomplete" } else { "invalid" },
+    HexDump::new(self.as_bytes(), self.len)
+)]
+pub struct SequenceError {
+    kind: ErrorKind,
+    offset: u64,
+    bytes: [u8; 4],
+    len: usize,
+    valid_len: usize,
+}
+
+impl SequenceError {
+    /// Error of `bytes`, which start at `offset` of the input and follow
+    /// `valid_len` bytes of valid text written into the output
+    pub(crate) fn new(kind: ErrorKind, bytes: &[u8], offset: u64, valid_len: usize) -> Self {
+        let mut sequence = [0; 4];
+        sequence[..bytes.len()].copy_from_slice(bytes);
+        Self {
+            kind,
+            offset,
+            bytes: sequence,
+            len: bytes.len(),
+            valid_len,
+        }
+    }
+
+    /// Returns either [`ErrorKind::InvalidUtf8`] or [`ErrorKind::Incomplete`].
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the offset of the sequence in the input.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the bytes of the invalid or incomplete sequence.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Returns the length of the valid text before the invalid sequence, which
+    /// has been written at the start of the output and is dropped along with
+    /// this error as [`DecodeError::as_valid_prefix`] is.
+    pub fn valid_len(&self) -> usize {
+        self.valid_len
+    }
+}
+
+/// The valid text and the bytes after the sequence are unknown so none are
+/// carried.
+impl From<SequenceError> for DecodeError {
+    fn from(err: SequenceError) -> Self {
+        match err.kind {
+            ErrorKind::Incomplete => {
+                DecodeError::incomplete_at(err.as_bytes().to_vec(), err.offset)
+            }
+            _ => {
+                let source = core::str::from_utf8(err.as_bytes()).unwrap_err();
+                DecodeError::invalid(source, err.offset, err.as_bytes())
+            }
+        }
+    }
+}
+
+/// Short dump of the bytes carried by an error, which is appended to its
+/// message.
+///
+/// Bytes are rendered in hex with the offending ones in brackets, followed by
+/// their printable ASCII characters like `: [E2 82] 24 |..$|`. Nothing is
+/// rendered without bytes.
+struct HexDump<'a> {
+    bytes: &'a [u8],
+    // Number of the leading bytes to highlight
+    bad: usize,
+}
+
+impl<'a> HexDump<'a> {
+    /// Maximum number of the bytes rendered
+    const MAX: usize = 8;
+
+    fn new(bytes: &'a [u8], bad: usize) -> Self {
+        Self { bytes, bad }
+    }
+}
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bytes.is_empty() {
+            return Ok(());
+        }
+        let shown = &self.bytes[..self.bytes.len().min(Self::MAX)];
+        let bad = self.bad.min(shown.len());
+        f.write_str(": ")?;
+        for (i, b) in shown.iter().enumerate() {
+            let sep = if i == 0 { "" } else { " " };
+            let open = if i == 0 && bad > 0 { "[" } else { "" };
+            let close = if i + 1 == bad { "]" } else { "" };
+            write!(f, "{}{}{:02X}{}", sep, open, b, close)?;
+        }
+        if shown.len() < self.bytes.len() {
+            f.write_str(" ..")?;
+        }
+        f.write_str(" |")?;
+        for &b in shown {
+            let c = if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            };
+            write!(f, "{}", c)?;
+        }
+        f.write_str("|")
+    }
+}
+
+/// Limit of the decoded text reported by [`DecodeError::LimitExceeded`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Limit {
+    /// Maximum number of bytes
+    Bytes(usize),
+    /// Maximum number of characters
+    Chars(usize),
+    /// Maximum number of bytes of a single framed message, such as an IRC
+    /// message
+    MessageBytes(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Bytes(n) => write!(f, "{} bytes", n),
+            Limit::Chars(n) => write!(f, "{} characters", n),
+            Limit::MessageBytes(n) => write!(f, "{} bytes per message", n),
+        }
+    }
+}
+
+/// How the decoder deals with invalid and truncated input
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ErrorPolicy {
+    /// Report invalid sequences as errors and drop the read they were found
+    /// in. An incomplete sequence at the end of the input is reported as
+    /// [`DecodeError::IncompleteUtf8Sequence`]. This is the default.
+    #[default]
+    Strict,
+    /// Replace each invalid sequence, as well as an incomplete sequence at the
+    /// end of the input, with U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+    /// Silently drop invalid sequences and an incomplete sequence at the end
+    /// of the input.
+    Skip,
+}
+
+impl ErrorPolicy {
+    /// Bytes which replace an invalid sequence unless it is reported
+    pub(crate) fn replacement(&self) -> Option<&'static [u8]> {
+        match self {
+            ErrorPolicy::Strict => None,
+            ErrorPolicy::Replace => Some("\u{FFFD}".as_bytes()),
+            ErrorPolicy::Skip => Some(b""),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn error_kind() {
+        let err = DecodeError::incomplete_at(vec![0xF0, 0x9F], 5);
+        assert_eq!(ErrorKind::Incomplete, err.kind());
+        assert!(err.is_incomplete());
+        assert!(!err.is_io());
+        assert_eq!(Some(&[0xF0, 0x9F][..]), err.as_invalid_bytes());
+        assert_eq!(Some(5), err.offset());
+        assert!(matches!(
+            err,
+            DecodeError::IncompleteUtf8Sequence { expected: 4, .. }
+        ));
+        assert_eq!(
+            "incomplete utf8 sequence at byte 5 (2 of 4 bytes): [F0 9F] |..|",
+            err.to_string()
+        );
+
+        let err = DecodeError::invalid_dropping(&[0x24, 0xFF, 0x24, 0xF0], 2);
+        assert_eq!(Some(3), err.offset());
+        assert_eq!(Some("$"), err.as_valid_prefix());
+        assert_eq!(Some(&[0xFF, 0x24, 0xF0][..]), err.as_invalid_bytes());
+        assert_eq!(
+            "invalid utf8 sequence at byte 3: [FF] 24 F0 |.$.|",
+            err.to_string()
+        );
+        let err = DecodeError::invalid_dropping(&[&[0xE2, 0x82, 0x24][..], &[b'a'; 8]].concat(), 0);
+        assert_eq!(
+            "invalid utf8 sequence at byte 0: [E2 82] 24 61 61 61 61 61 .. |..$aaaaa|",
+            err.to_string()
+        );
+        let bytes = vec![0xFF, 0x24];
+        let err = DecodeError::from(core::str::from_utf8(&bytes).unwrap_err());
+        assert_eq!(None, err.as_valid_prefix());
+        assert_eq!(None, err.as_invalid_bytes());
+
+        assert_eq!(err, err.clone());
+        assert_ne!(err, DecodeError::invalid_dropping(&[0x24, 0xFF], 2));
+
+        let err = DecodeError::BufferFull;
+        assert_eq!(ErrorKind::BufferFull, err.kind());
+        assert!(!err.is_transient());
+        assert!(DecodeError::Timeout(core::time::Duration::from_secs(1)).is_transient());
+        assert_eq!(None, err.as_invalid_bytes());
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn error_diagnostic() {
+        use alloc::string::ToString;
+        use miette::Diagnostic;
+
+        let err = DecodeError::invalid_dropping(&[0x24, 0xFF, 0x24], 2);
+        let code = err.code().unwrap().to_string();
+        assert_eq!("async_utf8_decoder::invalid_utf8_sequence", code);
+        let labels = err.labels().unwrap().collect::<Vec<_>>();
+        assert_eq!(1, labels[0].offset());
+
+        let mut report = String::new();
+        miette::NarratableReportHandler::new()
+            .render_report(&mut report, &err)
+            .unwrap();
+        assert!(report.contains("label at line 1, column 2: invalid sequence"));
+
+        let err = DecodeError::incomplete_at(vec![0xF0, 0x9F], 5);
+        assert!(err.help().is_some());
+        assert!(err.labels().is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn error_serialize() -> anyhow::Result<()> {
+        let err = DecodeError::incomplete_at(vec![0xF0, 0x9F], 5);
+        let json = serde_json::to_value(&err)?;
+        assert_eq!(
+            serde_json::json!({
+                "kind": "Incomplete",
+                "message": err.to_string(),
+                "offset": 5,
+                "bytes": "f09f",
+            }),
+            json
+        );
+
+        let json = serde_json::to_value(DecodeError::BufferFull)?;
+        assert_eq!(serde_json::Value::Null, json["offset"]);
+        assert_eq!(serde_json::Value::Null, json["bytes"]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn error_into_io() {
+        let err = DecodeError::from(futures_io::Error::from(futures_io::ErrorKind::BrokenPipe));
+        assert!(err.is_io());
+        assert!(!err.is_transient());
+        let interrupted = futures_io::Error::from(futures_io::ErrorKind::Interrupted);
+        assert!(DecodeError::from(interrupted).is_transient());
+        // I/O errors are compared by their kind
+        assert_eq!(err, err.clone());
+        assert_eq!(
+            err,
+            DecodeError::from(futures_io::Error::new(
+                futures_io::ErrorKind::BrokenPipe,
+                "closed"
+            ))
+        );
+        let err = err.into_io().unwrap();
+        assert_eq!(futures_io::ErrorKind::BrokenPipe, err.kind());
+
+        let err = futures_io::Error::from(DecodeError::BufferFull);
+        assert_eq!(futures_io::ErrorKind::InvalidData, err.kind());
+        let err = futures_io::Error::from(DecodeError::from(err));
+        assert_eq!(futures_io::ErrorKind::InvalidData, err.kind());
+    }
 }
@@ -0,0 +1,65 @@
+//! Extension methods of `AsyncRead` which create decoders.
+use crate::decoder::{ErrorPolicy, Utf8Decoder};
+use crate::lines::Utf8Lines;
+use futures_io::AsyncRead;
+
+/// Extension trait which adds decoding methods to every [`AsyncRead`].
+pub trait Utf8DecoderExt: AsyncRead {
+    /// Decode this reader into a string stream. See [`Utf8Decoder::new`].
+    fn utf8_decode(self) -> Utf8Decoder<Self>
+    where
+        Self: Sized,
+    {
+        Utf8Decoder::new(self)
+    }
+
+    /// Decode this reader into a string stream which replaces invalid input
+    /// with U+FFFD. See [`ErrorPolicy::Replace`].
+    fn utf8_decode_lossy(self) -> Utf8Decoder<Self>
+    where
+        Self: Sized,
+    {
+        let mut decoder = Utf8Decoder::new(self);
+        decoder.set_error_policy(ErrorPolicy::Replace);
+        decoder
+    }
+
+    /// Decode this reader into a stream of lines. See [`Utf8Lines`].
+    fn utf8_lines(self) -> Utf8Lines<Utf8Decoder<Self>>
+    where
+        Self: Sized,
+    {
+        Utf8Lines::new(Utf8Decoder::new(self))
+    }
+}
+
+impl<R: AsyncRead + ?Sized> Utf8DecoderExt for R {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn ext_decode() -> Result<()> {
+        let chunks = || {
+            let chunks: Vec<io::Result<Vec<u8>>> =
+                vec![Ok(b"a\r\nb".to_vec()), Ok(vec![0xFF, b'\n', b'c'])];
+            stream::iter(chunks).into_async_read()
+        };
+
+        let decoded = chunks().utf8_decode().try_collect::<String>().await;
+        assert!(decoded.is_err());
+
+        let decoded = chunks().utf8_decode_lossy().try_collect::<String>().await?;
+        assert_eq!("a\r\nb\u{FFFD}\nc", decoded);
+
+        let lines = io::Cursor::new("a\r\nb\n\nc").utf8_lines();
+        let lines = lines.try_collect::<Vec<_>>().await?;
+        assert_eq!(vec!["a", "b", "", "c"], lines);
+
+        Ok(())
+    }
+}
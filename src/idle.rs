@@ -0,0 +1,128 @@
+//! Stream of decoded text and idle notifications.
+use crate::decoder::{Result, Utf8Decoder};
+use crate::timer::Deadline;
+use futures_core::{ready, Stream};
+use futures_io::AsyncRead;
+use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Item of [`IdleEvents`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecoderEvent {
+    /// Decoded text
+    Text(String),
+    /// No bytes have arrived for the configured duration
+    Idle,
+}
+
+pin_project! {
+    /// Stream created by [`Utf8Decoder::into_idle_events`] which injects
+    /// [`DecoderEvent::Idle`] when no bytes have arrived for a while.
+    ///
+    /// It is yielded once per idle period. Any byte which arrives afterwards,
+    /// even a part of an incomplete sequence, ends the period and the text is
+    /// yielded as usual.
+    pub struct IdleEvents<R> {
+        #[pin]
+        decoder: Utf8Decoder<R>,
+        deadline: Deadline,
+        idle: bool,
+    }
+}
+
+impl<R> IdleEvents<R> {
+    pub(crate) fn new(decoder: Utf8Decoder<R>, idle: Duration) -> Self {
+        Self {
+            decoder,
+            deadline: Deadline::new(idle),
+            idle: false,
+        }
+    }
+
+    /// Consumes this stream, returning the underlying decoder.
+    pub fn into_inner(self) -> Utf8Decoder<R> {
+        self.decoder
+    }
+
+    /// Acquires a reference to the underlying decoder.
+    pub fn get_ref(&self) -> &Utf8Decoder<R> {
+        &self.decoder
+    }
+
+    /// Acquires a mutable reference to the underlying decoder.
+    pub fn get_mut(&mut self) -> &mut Utf8Decoder<R> {
+        &mut self.decoder
+    }
+}
+
+impl<R> Stream for IdleEvents<R>
+where
+    R: AsyncRead,
+{
+    type Item = Result<DecoderEvent>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let mut this = self.project();
+        let reads = this.decoder.stats().reads;
+        if let Poll::Ready(item) = this.decoder.as_mut().poll_next(cx) {
+            this.deadline.restart();
+            *this.idle = false;
+            return Poll::Ready(item.map(|r| r.map(DecoderEvent::Text)));
+        }
+        if this.decoder.stats().reads != reads {
+            this.deadline.restart();
+            *this.idle = false;
+        }
+        if *this.idle {
+            return Poll::Pending;
+        }
+        ready!(this.deadline.poll_expired(cx));
+        *this.idle = true;
+        Poll::Ready(Some(Ok(DecoderEvent::Idle)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::channel::mpsc;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn idle_events_between_text() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut events =
+            Utf8Decoder::new(rx.into_async_read()).into_idle_events(Duration::from_millis(10));
+
+        tx.send(Ok(vec![0x24])).await?;
+        assert_eq!(
+            DecoderEvent::Text("$".into()),
+            events.next().await.unwrap()?
+        );
+        assert_eq!(DecoderEvent::Idle, events.next().await.unwrap()?);
+        // Only one notification is yielded per idle period
+        let next = future::select(
+            events.next(),
+            futures_timer::Delay::new(Duration::from_millis(30)),
+        )
+        .await;
+        assert!(matches!(next, future::Either::Right(_)));
+
+        tx.send(Ok(vec![0x24])).await?;
+        assert_eq!(
+            DecoderEvent::Text("$".into()),
+            events.next().await.unwrap()?
+        );
+        drop(tx);
+        assert!(events.next().await.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+//! Stream of decoded text which reports errors as I/O errors.
+use crate::err_into::ErrInto;
+
+/// Stream created by [`Utf8Decoder::into_io_stream`] which yields
+/// `io::Result<String>` instead of [`Result<String>`].
+///
+/// It plugs the decoder into frameworks which only accept streams of I/O
+/// results. Errors are converted with the `From<DecodeError>` implementation
+/// of `io::Error`: I/O errors of the reader are passed through and invalid
+/// input is reported as [`InvalidData`] which wraps the [`DecodeError`].
+///
+/// [`Utf8Decoder::into_io_stream`]: crate::Utf8Decoder::into_io_stream
+/// [`Result<String>`]: crate::Result
+/// [`InvalidData`]: futures_io::ErrorKind::InvalidData
+/// [`DecodeError`]: crate::DecodeError
+pub type IoStream<R> = ErrInto<R, futures_io::Error>;
+
+#[cfg(test)]
+mod tests {
+    use crate::{DecodeError, Utf8Decoder};
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn io_stream_reports_invalid_data() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![
+            Ok(vec![0x24]),
+            Ok(vec![0xFF]),
+            Err(io::ErrorKind::BrokenPipe.into()),
+        ];
+        let decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+        let mut stream = decoder.into_io_stream();
+
+        assert_eq!("$", stream.next().await.unwrap()?);
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        let inner = err.get_ref().unwrap().downcast_ref::<DecodeError>();
+        assert!(matches!(
+            inner,
+            Some(DecodeError::Utf8Error { offset: 1, .. })
+        ));
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(io::ErrorKind::BrokenPipe, err.kind());
+
+        Ok(())
+    }
+}
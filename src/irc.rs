@@ -0,0 +1,205 @@
+//! IRC message framing on top of a decoded string stream.
+use crate::decoder::Result;
+use crate::error::{DecodeError, Limit};
+use crate::line_buffer::LineBuffer;
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Maximum byte length of an IRC message including the trailing CRLF (RFC 1459)
+pub const MAX_MESSAGE_LEN: usize = 512;
+/// Maximum byte length of an IRCv3 tag section including the leading '@' and trailing space
+pub const MAX_TAGS_LEN: usize = 8191;
+
+pin_project! {
+    /// Stream adapter which frames decoded text into IRC messages.
+    ///
+    /// Each item is a single message without its line terminator. Messages are
+    /// terminated by CRLF (a bare LF is accepted as well) and empty messages are
+    /// skipped. A message longer than [`MAX_MESSAGE_LEN`] yields
+    /// [`DecodeError::LimitExceeded`] with [`Limit::MessageBytes`] and is discarded
+    /// up to its terminator, after which framing resumes with the next message.
+    pub struct IrcMessages<S> {
+        #[pin]
+        stream: S,
+        lines: LineBuffer,
+        tags: bool,
+        discarding: bool,
+        terminated: bool,
+    }
+}
+
+impl<S> IrcMessages<S> {
+    /// Create a new IRC message framer from a decoded string `stream`
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            lines: LineBuffer::default(),
+            tags: false,
+            discarding: false,
+            terminated: false,
+        }
+    }
+
+    /// Create a new IRC message framer which is aware of IRCv3 message tags.
+    ///
+    /// The tag section of a message starting with '@' is limited by [`MAX_TAGS_LEN`]
+    /// separately from the rest of the message.
+    pub fn with_tags(stream: S) -> Self {
+        Self {
+            tags: true,
+            ..Self::new(stream)
+        }
+    }
+
+    /// Consumes this framer, returning the underlying stream.
+    ///
+    /// Text which has been received but not framed yet is discarded.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Acquires a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+fn check_length(line: &str, tags: bool) -> std::result::Result<(), DecodeError> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let (tags_len, body_len) = match line.find(' ') {
+        Some(i) if tags && line.starts_with('@') => (i + 1, line.len() - i - 1),
+        None if tags && line.starts_with('@') => (line.len(), 0),
+        _ => (0, line.len()),
+    };
+    if tags_len > MAX_TAGS_LEN {
+        return Err(DecodeError::LimitExceeded(Limit::MessageBytes(
+            MAX_TAGS_LEN,
+        )));
+    }
+    // The CRLF terminator counts towards the limit
+    if body_len + 2 > MAX_MESSAGE_LEN {
+        return Err(DecodeError::LimitExceeded(Limit::MessageBytes(
+            MAX_MESSAGE_LEN,
+        )));
+    }
+    Ok(())
+}
+
+impl<S> Stream for IrcMessages<S>
+where
+    S: Stream<Item = Result<String>>,
+{
+    type Item = Result<String>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let mut this = self.project();
+        loop {
+            if *this.discarding {
+                *this.discarding = !this.lines.discard_line();
+            }
+            if !*this.discarding {
+                if let Some(line) = this.lines.next_line() {
+                    if line.is_empty() {
+                        continue;
+                    }
+                    return Poll::Ready(Some(check_length(&line, *this.tags).map(|_| line)));
+                }
+                // Do not wait for the terminator of a message which is already too long
+                if let Err(err) = check_length(this.lines.as_str(), *this.tags) {
+                    *this.discarding = !this.lines.discard_line();
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+            if *this.terminated {
+                return Poll::Ready(None);
+            }
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(Ok(decoded)) => this.lines.push(&decoded),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {
+                    *this.terminated = true;
+                    // Emit the unterminated last message rather than losing it
+                    let rest = this.lines.take();
+                    if !*this.discarding && !rest.is_empty() {
+                        return Poll::Ready(Some(check_length(&rest, *this.tags).map(|_| rest)));
+                    }
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    fn framer(chunks: Vec<&[u8]>) -> IrcMessages<Utf8Decoder<impl io::AsyncRead>> {
+        let chunks = chunks
+            .into_iter()
+            .map(|c| Ok(c.to_vec()))
+            .collect::<Vec<io::Result<Vec<u8>>>>();
+        IrcMessages::new(Utf8Decoder::new(stream::iter(chunks).into_async_read()))
+    }
+
+    #[async_std::test]
+    async fn irc_messages_split_across_reads() -> Result<()> {
+        let mut messages = framer(vec![
+            b"PING :a\r",
+            b"\nPRIVMSG #c :\xF0\x9F",
+            b"\x92\x96\r\n\r\nQUIT",
+        ]);
+
+        assert_eq!("PING :a", messages.next().await.unwrap()?);
+        assert_eq!("PRIVMSG #c :💖", messages.next().await.unwrap()?);
+        assert_eq!("QUIT", messages.next().await.unwrap()?);
+        assert!(messages.next().await.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn irc_messages_too_long() -> Result<()> {
+        let long = format!("PRIVMSG #c :{}\r\nPING\r\n", "x".repeat(MAX_MESSAGE_LEN));
+        let mut messages = framer(vec![long.as_bytes()]);
+
+        assert!(matches!(
+            messages.next().await.unwrap(),
+            Err(DecodeError::LimitExceeded(Limit::MessageBytes(
+                MAX_MESSAGE_LEN
+            )))
+        ));
+        assert_eq!("PING", messages.next().await.unwrap()?);
+        assert!(messages.next().await.is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn irc_messages_with_tags() -> Result<()> {
+        let tags = format!("@{}", "a".repeat(MAX_MESSAGE_LEN));
+        let line = format!("{} PRIVMSG #c :hi\r\n", tags);
+        let chunks = vec![Ok(line.clone().into_bytes())];
+        let reader = stream::iter(chunks).into_async_read();
+        let mut messages = IrcMessages::with_tags(Utf8Decoder::new(reader));
+
+        assert_eq!(line.trim_end(), messages.next().await.unwrap()?);
+        assert!(messages.next().await.is_none());
+
+        Ok(())
+    }
+}
@@ -5,46 +5,275 @@
 //!
 //! ## Example
 //!
+//! `Utf8Decoder::next_timeout` used below requires the `timer` feature.
+//!
 //! ```
-//! # use anyhow::{anyhow, Result};
+//! # use anyhow::Result;
 //! # use futures::prelude::*;
 //! # use futures::executor;
-//! # async fn timeout<T>(future: impl Future<Output = T> + Unpin) -> Result<T> {
-//! #     let mut future = future.fuse();
-//! #     let mut sleep = futures_timer::Delay::new(std::time::Duration::from_millis(100)).fuse();
-//! #     futures::select! {
-//! #         r = future => Ok(r),
-//! #         _ = sleep => Err(anyhow!("Timeout")),
-//! #     }
-//! # }
+//! # #[cfg(feature = "timer")]
 //! # fn main() -> Result<()> {
 //! # executor::block_on(async {
+//! use std::time::Duration;
 //! use futures::io;
 //! use futures::channel::mpsc;
-//! use async_utf8_decoder::Utf8Decoder;
+//! use async_utf8_decoder::{DecodeError, Utf8Decoder};
 //!
+//! let timeout = Duration::from_millis(100);
 //! let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
-//! let mut decoder = Utf8Decoder::new(rx.into_async_read());
+//! let mut decoder = Utf8Decoder::from_stream(rx);
 //!
 //! tx.send(Ok(vec![240])).await?;
-//! assert!(timeout(decoder.next()).await.is_err());
+//! assert!(matches!(decoder.next_timeout(timeout).await, Some(Err(DecodeError::Timeout(_)))));
 //! tx.send(Ok(vec![159])).await?;
-//! assert!(timeout(decoder.next()).await.is_err());
+//! assert!(matches!(decoder.next_timeout(timeout).await, Some(Err(DecodeError::Timeout(_)))));
 //! tx.send(Ok(vec![146])).await?;
-//! assert!(timeout(decoder.next()).await.is_err());
+//! assert!(matches!(decoder.next_timeout(timeout).await, Some(Err(DecodeError::Timeout(_)))));
 //! tx.send(Ok(vec![150])).await?;
-//! assert_eq!("💖", timeout(decoder.next()).await?.unwrap()?);
-//! assert!(timeout(decoder.next()).await.is_err());
+//! assert_eq!("💖", decoder.next_timeout(timeout).await.unwrap()?);
+//! assert!(matches!(decoder.next_timeout(timeout).await, Some(Err(DecodeError::Timeout(_)))));
 //! # Ok(()) as Result<()>
 //! # })?;
 //! # Ok(())
 //! # }
+//! # #[cfg(not(feature = "timer"))]
+//! # fn main() {}
 //! ```
 //!
+//! ## Feature flags
+//!
+//! - `std` (default): Everything but [`Utf8DecoderCore`] and [`DecodeError`]. Disable it to
+//!   build the crate with `no_std` and `alloc`
+//! - `unchecked` (default): Skip the second validation of validated bytes with `unsafe` code.
+//!   Disable it to build the crate with `#![forbid(unsafe_code)]`
+//! - `async_channel`: Decode the receiver of an `async-channel` channel with [`Utf8Decoder::from_receiver`]
+//! - `asynchronous_codec`: Frame bytes into text with the [`Utf8Codec`] of `asynchronous-codec`
+//! - `compact_str`: Yield `CompactString` items with [`Utf8Decoder::into_compact`]
+//! - `defmt`: Implement `defmt::Format` for [`DecodeError`], [`Utf8DecoderCore`], and their types
+//!   to log them on microcontrollers
+//! - `http_body`: Decode an `http-body` body such as the body of a `hyper` request with
+//!   [`Utf8Decoder::from_body`], and send text as a body with [`TextBody`]
+//! - `miette`: Implement `miette::Diagnostic` for [`DecodeError`]
+//! - `monoio`: Decode a `monoio` reader which owns the buffer during a read with
+//!   [`OwnedUtf8Decoder::from_monoio`]
+//! - `nightly`: Scan ASCII bytes with `portable_simd` before the validation (requires a nightly compiler)
+//! - `rayon`: Validate very large reads in parallel segments with `rayon`
+//! - `reqwest`: Decode the body of a `reqwest` response with [`Utf8Decoder::from_response`]
+//! - `serde`: Serialize and deserialize [`DecoderState`], [`DecoderStats`], and the option types,
+//!   and serialize [`DecodeError`] into a structured form
+//! - `timer`: Time out stalled reads and incomplete sequences, and notify idle periods
+//!   (`Utf8Decoder::next_timeout`, `Utf8Decoder::into_idle_events`, and the timeout setters)
+//! - `tokio`: Decode a `tokio::io::AsyncRead` directly with [`Utf8Decoder::from_tokio`], and the
+//!   receiver of a `tokio::sync::mpsc` channel with [`Utf8Decoder::from_receiver`]
+//! - `tokio_uring`: Decode the files and streams of `tokio-uring` with `OwnedUtf8Decoder::from_uring`
+//!   (Linux only)
+//! - `tokio_util`: Frame bytes into text with the [`Utf8Codec`] of `tokio-util`
+//! - `tungstenite`: Decode the messages of a WebSocket of `tungstenite` with [`WebSocketText`]
+//! - `unstable`: Implement `std::async_iter::AsyncIterator` for [`Utf8Decoder`] (requires a nightly compiler)
+//! - `wasm`: Decode a JavaScript `ReadableStream` with [`Utf8Decoder::from_readable_stream`]
+//!
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(not(feature = "unchecked"), forbid(unsafe_code))]
+#![cfg_attr(feature = "nightly", feature(portable_simd))]
+#![cfg_attr(feature = "unstable", feature(async_iterator))]
+extern crate alloc;
+
+mod ascii;
+#[cfg(feature = "std")]
+pub mod blocking;
+#[cfg(feature = "http_body")]
+pub mod body;
+#[cfg(feature = "std")]
+pub mod boundary;
+#[cfg(feature = "std")]
+pub mod buf_decoder;
+#[cfg(feature = "std")]
+mod buffer;
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod chain;
+#[cfg(any(feature = "tokio", feature = "async_channel"))]
+pub mod channel;
+#[cfg(feature = "std")]
+pub mod chunk;
+#[cfg(feature = "std")]
+pub mod chunked;
+#[cfg(any(feature = "tokio_util", feature = "asynchronous_codec"))]
+pub mod codec;
+#[cfg(feature = "compact_str")]
+pub mod compact;
+#[cfg(feature = "std")]
 pub mod decoder;
+#[cfg(feature = "std")]
+pub mod dot_stuffed;
+#[cfg(feature = "std")]
+pub mod err_into;
 pub mod error;
+#[cfg(feature = "std")]
+pub mod ext;
+#[cfg(feature = "timer")]
+pub mod idle;
+#[cfg(feature = "std")]
+pub mod io_stream;
+#[cfg(feature = "std")]
+pub mod irc;
+#[cfg(feature = "std")]
+mod line_buffer;
+#[cfg(feature = "std")]
+pub mod lines;
+#[cfg(feature = "std")]
+pub mod meter;
+#[cfg(feature = "monoio")]
+pub mod monoio_io;
+#[cfg(feature = "std")]
+pub mod observer;
+#[cfg(feature = "std")]
+pub mod owned;
+#[cfg(feature = "std")]
+pub mod pool;
+#[cfg(feature = "std")]
+pub mod push;
+pub mod sans_io;
+#[cfg(feature = "std")]
+pub mod slice;
+#[cfg(feature = "std")]
+pub mod stack;
+#[cfg(feature = "std")]
+pub mod state;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod stream_reader;
+#[cfg(feature = "std")]
+pub mod take;
+#[cfg(feature = "std")]
+mod timer;
+#[cfg(feature = "tokio")]
+pub mod tokio_io;
+#[cfg(all(feature = "tokio_uring", target_os = "linux"))]
+pub mod uring;
+mod validated;
+mod validation;
+#[cfg(feature = "std")]
+pub mod warning;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "tungstenite")]
+pub mod websocket;
 
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use blocking::{BlockingIter, SyncUtf8Decoder};
+#[cfg(feature = "http_body")]
+#[doc(inline)]
+pub use body::{BodyStream, TextBody};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use boundary::{Boundaries, Boundary, Segment};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use buf_decoder::Utf8BufDecoder;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use builder::Utf8DecoderBuilder;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use chain::ChainReader;
+#[cfg(any(feature = "tokio", feature = "async_channel"))]
+#[doc(inline)]
+pub use channel::ReceiverStream;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use chunk::{Utf8Chunk, Utf8Chunks};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use chunked::ChunkedReader;
+#[cfg(any(feature = "tokio_util", feature = "asynchronous_codec"))]
+#[doc(inline)]
+pub use codec::Utf8Codec;
+#[cfg(feature = "compact_str")]
+#[doc(inline)]
+pub use compact::CompactStrings;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use decoder::{
+    decode_all, decode_to_string_lossy, default_capacity, set_default_capacity, Autotune,
+    BoxUtf8Decoder, ReadStrategy, Utf8Decoder, ZeroReadPolicy,
+};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use dot_stuffed::DotStuffed;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use err_into::ErrInto;
+#[doc(inline)]
+pub use error::{DecodeError, ErrorKind, ErrorPolicy, Limit, Result, SequenceError};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use ext::Utf8DecoderExt;
+#[cfg(feature = "timer")]
+#[doc(inline)]
+pub use idle::{DecoderEvent, IdleEvents};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use io_stream::IoStream;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use irc::IrcMessages;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use lines::Utf8Lines;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use meter::{Clock, SystemClock, Throughput, ThroughputMeter};
+#[cfg(feature = "monoio")]
+#[doc(inline)]
+pub use monoio_io::MonoioReader;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use observer::DecoderObserver;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use owned::{OwnedRead, OwnedUtf8Decoder};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use pool::{BufferPool, DecoderPool};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use push::PushUtf8Decoder;
+#[doc(inline)]
+pub use sans_io::Utf8DecoderCore;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use slice::Utf8Slices;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use stack::StackUtf8Decoder;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use state::DecoderState;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use stats::DecoderStats;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use stream_reader::StreamReader;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use take::TakeReader;
+#[cfg(feature = "tokio")]
+#[doc(inline)]
+pub use tokio_io::TokioReader;
+#[cfg(all(feature = "tokio_uring", target_os = "linux"))]
+#[doc(inline)]
+pub use uring::UringReader;
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use warning::{DecodeWarning, Warnings};
+#[cfg(feature = "wasm")]
 #[doc(inline)]
-pub use decoder::{Result, Utf8Decoder};
+pub use wasm::JsChunks;
+#[cfg(feature = "tungstenite")]
 #[doc(inline)]
-pub use error::DecodeError;
+pub use websocket::WebSocketText;
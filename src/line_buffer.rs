@@ -0,0 +1,123 @@
+/// Accumulates decoded text and splits it into LF or CRLF terminated lines.
+#[derive(Debug, Default)]
+pub(crate) struct LineBuffer {
+    buf: String,
+    // Position of the text which has not been returned as a line yet
+    start: usize,
+    // Position up to which 'buf' is known to have no '\n'
+    scanned: usize,
+}
+
+impl LineBuffer {
+    pub(crate) fn push(&mut self, s: &str) {
+        self.compact();
+        self.buf.push_str(s);
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.buf[self.start..]
+    }
+
+    /// Return the next complete line without its line terminator.
+    ///
+    /// The line is copied out so that its capacity fits its length, and the
+    /// consumed text is dropped only once it makes up half of the buffer, so
+    /// splitting a chunk of many lines takes linear time.
+    pub(crate) fn next_line(&mut self) -> Option<String> {
+        let scanned = self.scanned.max(self.start);
+        match self.buf[scanned..].find('\n') {
+            Some(i) => {
+                let end = scanned + i;
+                let line = &self.buf[self.start..end];
+                let line = line.strip_suffix('\r').unwrap_or(line).to_owned();
+                self.start = end + 1;
+                self.scanned = self.start;
+                if self.start > self.buf.len() / 2 {
+                    self.compact();
+                }
+                Some(line)
+            }
+            None => {
+                self.scanned = self.buf.len();
+                None
+            }
+        }
+    }
+
+    /// Drop the text up to and including the next '\n' and report whether one was found.
+    pub(crate) fn discard_line(&mut self) -> bool {
+        match self.as_str().find('\n') {
+            Some(i) => {
+                self.start += i + 1;
+                self.scanned = self.start;
+                true
+            }
+            None => {
+                self.buf.clear();
+                self.start = 0;
+                self.scanned = 0;
+                false
+            }
+        }
+    }
+
+    /// Take all the text which has not been returned as a line yet.
+    pub(crate) fn take(&mut self) -> String {
+        self.compact();
+        self.scanned = 0;
+        std::mem::take(&mut self.buf)
+    }
+
+    /// Drop the text which has been returned as lines
+    fn compact(&mut self) {
+        if self.start > 0 {
+            self.buf.drain(..self.start);
+            self.scanned -= self.start.min(self.scanned);
+            self.start = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn split(count: usize) -> (Vec<String>, std::time::Duration) {
+        let mut lines = LineBuffer::default();
+        lines.push(&"0123456789\n".repeat(count));
+        let started = Instant::now();
+        let split = std::iter::from_fn(|| lines.next_line()).collect::<Vec<_>>();
+        (split, started.elapsed())
+    }
+
+    #[test]
+    fn line_buffer_splits_chunk_in_linear_time() {
+        let (lines, _) = split(8_000);
+        assert_eq!(8_000, lines.len());
+        for line in &lines {
+            assert_eq!("0123456789", line);
+            assert!(line.capacity() <= 2 * line.len());
+        }
+
+        // Quadratic splitting takes 16 times longer for 4 times the lines
+        let (_, small) = split(32_000);
+        let (_, large) = split(128_000);
+        assert!(large < small * 10, "{:?} vs {:?}", small, large);
+    }
+
+    #[test]
+    fn line_buffer_keeps_partial_line() {
+        let mut lines = LineBuffer::default();
+        lines.push("a\r\nb");
+        assert_eq!(Some("a".to_string()), lines.next_line());
+        assert_eq!(None, lines.next_line());
+        assert_eq!("b", lines.as_str());
+        lines.push("c\nd\ne");
+        assert_eq!(Some("bc".to_string()), lines.next_line());
+        assert!(lines.discard_line());
+        assert!(!lines.discard_line());
+        lines.push("f");
+        assert_eq!("f", lines.take());
+    }
+}
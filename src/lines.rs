@@ -0,0 +1,119 @@
+//! Line framing on top of a decoded string stream.
+use crate::decoder::Result;
+use crate::line_buffer::LineBuffer;
+use futures_core::{ready, FusedStream, Stream};
+use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Stream adapter which splits decoded text into lines.
+    ///
+    /// Each item is a single line without its LF or CRLF terminator. The last
+    /// line is yielded even if it is not terminated, unless it is empty.
+    pub struct Utf8Lines<S> {
+        #[pin]
+        stream: S,
+        lines: LineBuffer,
+        terminated: bool,
+    }
+}
+
+impl<S> Utf8Lines<S> {
+    /// Create a new line splitter from a decoded string `stream`
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            lines: LineBuffer::default(),
+            terminated: false,
+        }
+    }
+
+    /// Consumes this splitter, returning the underlying stream.
+    ///
+    /// Text which has been received but not yielded as a line yet is discarded.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Acquires a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+impl<S> Stream for Utf8Lines<S>
+where
+    S: Stream<Item = Result<String>>,
+{
+    type Item = Result<String>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let mut this = self.project();
+        loop {
+            if let Some(line) = this.lines.next_line() {
+                return Poll::Ready(Some(Ok(line)));
+            }
+            if *this.terminated {
+                return Poll::Ready(None);
+            }
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(Ok(decoded)) => this.lines.push(&decoded),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {
+                    *this.terminated = true;
+                    let rest = this.lines.take();
+                    if !rest.is_empty() {
+                        return Poll::Ready(Some(Ok(rest)));
+                    }
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+impl<S> FusedStream for Utf8Lines<S>
+where
+    S: Stream<Item = Result<String>>,
+{
+    fn is_terminated(&self) -> bool {
+        self.terminated && self.lines.as_str().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn lines_split_large_read() -> Result<()> {
+        // One 64 KiB read of short lines
+        let count = 64 * 1024 / 11;
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok("0123456789\n".repeat(count).into())];
+        let reader = stream::iter(chunks).into_async_read();
+        let lines = Utf8Lines::new(Utf8Decoder::with_capacity(64 * 1024, reader))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(count, lines.len());
+        for line in &lines {
+            assert_eq!("0123456789", line);
+            // Lines do not hold on to the capacity of the read
+            assert!(line.capacity() <= 2 * line.len());
+        }
+
+        Ok(())
+    }
+}
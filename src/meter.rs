@@ -0,0 +1,162 @@
+//! Rolling throughput measurement of a decoder.
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Source of the current time of a [`ThroughputMeter`]
+pub trait Clock {
+    /// Returns the time elapsed since an arbitrary fixed point.
+    fn now(&self) -> Duration;
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> Duration,
+{
+    fn now(&self) -> Duration {
+        self()
+    }
+}
+
+/// [`Clock`] backed by [`Instant`]
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Decode throughput over the window of a [`ThroughputMeter`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    /// Decoded bytes per second
+    pub bytes_per_sec: f64,
+    /// Decoded items per second
+    pub items_per_sec: f64,
+}
+
+/// Meter of the decode throughput over a rolling window.
+///
+/// Attach it with [`Utf8Decoder::set_throughput_meter`] and read the current
+/// value with [`Utf8Decoder::throughput`].
+///
+/// [`Utf8Decoder::set_throughput_meter`]: crate::Utf8Decoder::set_throughput_meter
+/// [`Utf8Decoder::throughput`]: crate::Utf8Decoder::throughput
+pub struct ThroughputMeter {
+    clock: Box<dyn Clock + Send + Sync>,
+    window: Duration,
+    // Time and length of each item decoded within the window
+    samples: VecDeque<(Duration, usize)>,
+}
+
+impl ThroughputMeter {
+    /// Create a new meter over `window` with the [`SystemClock`]
+    pub fn new(window: Duration) -> Self {
+        ThroughputMeter::with_clock(window, SystemClock::default())
+    }
+
+    /// Create a new meter over `window` which reads the time from `clock`
+    pub fn with_clock<C>(window: Duration, clock: C) -> Self
+    where
+        C: Clock + Send + Sync + 'static,
+    {
+        Self {
+            clock: Box::new(clock),
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Record a decoded item of `len` bytes
+    pub(crate) fn record(&mut self, len: usize) {
+        let now = self.clock.now();
+        self.evict(now);
+        self.samples.push_back((now, len));
+    }
+
+    /// Returns the throughput over the window ending now.
+    pub fn throughput(&mut self) -> Throughput {
+        let now = self.clock.now();
+        self.evict(now);
+        let secs = self.window.as_secs_f64();
+        if secs == 0.0 {
+            return Throughput::default();
+        }
+        let bytes: usize = self.samples.iter().map(|(_, len)| len).sum();
+        Throughput {
+            bytes_per_sec: bytes as f64 / secs,
+            items_per_sec: self.samples.len() as f64 / secs,
+        }
+    }
+
+    fn evict(&mut self, now: Duration) {
+        while let Some((t, _)) = self.samples.front() {
+            if now.saturating_sub(*t) < self.window {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+}
+
+impl fmt::Debug for ThroughputMeter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThroughputMeter")
+            .field("window", &self.window)
+            .field("samples", &self.samples.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    #[async_std::test]
+    async fn meter_reports_rolling_throughput() -> Result<()> {
+        let millis = Arc::new(AtomicU64::new(0));
+        let clock = {
+            let millis = millis.clone();
+            move || Duration::from_millis(millis.load(Ordering::SeqCst))
+        };
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24; 100]), Ok(vec![0x24; 300])];
+        let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+        decoder.set_throughput_meter(Some(ThroughputMeter::with_clock(
+            Duration::from_secs(2),
+            clock,
+        )));
+
+        decoder.next().await.unwrap()?;
+        millis.store(1000, Ordering::SeqCst);
+        decoder.next().await.unwrap()?;
+        let throughput = decoder.throughput().unwrap();
+        assert_eq!(200.0, throughput.bytes_per_sec);
+        assert_eq!(1.0, throughput.items_per_sec);
+
+        // The first item falls out of the window
+        millis.store(2500, Ordering::SeqCst);
+        let throughput = decoder.throughput().unwrap();
+        assert_eq!(150.0, throughput.bytes_per_sec);
+        assert_eq!(0.5, throughput.items_per_sec);
+
+        Ok(())
+    }
+}
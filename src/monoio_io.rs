@@ -0,0 +1,73 @@
+//! Reader which adapts a `monoio::io::AsyncReadRent`.
+use crate::owned::{OwnedRead, OwnedUtf8Decoder};
+use monoio::io::AsyncReadRent;
+use std::io;
+
+/// Reader which reads from a [`monoio::io::AsyncReadRent`] for
+/// [`OwnedUtf8Decoder`].
+///
+/// It is created by [`OwnedUtf8Decoder::from_monoio`].
+#[derive(Debug)]
+pub struct MonoioReader<R> {
+    reader: R,
+}
+
+impl<R> MonoioReader<R> {
+    /// Create a new reader which reads from `reader`
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Consumes this reader, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Acquires a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R> OwnedRead for MonoioReader<R>
+where
+    R: AsyncReadRent,
+{
+    async fn read_owned(&mut self, buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        self.reader.read(buf).await
+    }
+}
+
+impl<R> OwnedUtf8Decoder<MonoioReader<R>>
+where
+    R: AsyncReadRent,
+{
+    /// Create a new incremental UTF-8 decoder from a `monoio` `reader`
+    pub fn from_monoio(reader: R) -> Self {
+        OwnedUtf8Decoder::new(MonoioReader::new(reader))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OwnedUtf8Decoder;
+    use anyhow::Result;
+
+    #[async_std::test]
+    async fn monoio_reader_decode() -> Result<()> {
+        let text = "$ü€💖".repeat(4);
+        let mut decoder = OwnedUtf8Decoder::from_monoio(text.as_bytes());
+        let mut decoded = String::new();
+        while let Some(chunk) = decoder.next().await {
+            decoded.push_str(&chunk?);
+        }
+        assert_eq!(text, decoded);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+//! Hooks into the poll loop of a decoder.
+use std::fmt;
+
+/// Observer of the events in a [`Utf8Decoder`] attached with
+/// [`Utf8Decoder::set_observer`].
+///
+/// Every callback does nothing by default, so implementors only override the
+/// events they are interested in. Callbacks are invoked synchronously from the
+/// poll loop and should return quickly.
+///
+/// [`Utf8Decoder`]: crate::Utf8Decoder
+/// [`Utf8Decoder::set_observer`]: crate::Utf8Decoder::set_observer
+pub trait DecoderObserver {
+    /// Called after a read of `n` bytes from the reader.
+    fn on_read(&mut self, n: usize) {
+        let _ = n;
+    }
+
+    /// Called with each decoded item before it is yielded.
+    fn on_chunk(&mut self, chunk: &str) {
+        let _ = chunk;
+    }
+
+    /// Called for each invalid sequence, including an incomplete sequence at
+    /// the end of the input. `replaced` tells whether it has been replaced with
+    /// U+FFFD rather than reported or skipped.
+    fn on_invalid(&mut self, replaced: bool) {
+        let _ = replaced;
+    }
+
+    /// Called each time the reader reports the end of the input.
+    fn on_eof(&mut self) {}
+}
+
+impl fmt::Debug for dyn DecoderObserver + Send + Sync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DecoderObserver")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorPolicy, Utf8Decoder};
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl DecoderObserver for Recorder {
+        fn on_read(&mut self, n: usize) {
+            self.0.lock().unwrap().push(format!("read {}", n));
+        }
+
+        fn on_chunk(&mut self, chunk: &str) {
+            self.0.lock().unwrap().push(format!("chunk {}", chunk));
+        }
+
+        fn on_invalid(&mut self, replaced: bool) {
+            self.0.lock().unwrap().push(format!("invalid {}", replaced));
+        }
+
+        fn on_eof(&mut self) {
+            self.0.lock().unwrap().push("eof".to_string());
+        }
+    }
+
+    #[async_std::test]
+    async fn observer_receives_events() -> Result<()> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24, 0xFF]), Ok(vec![0xE2, 0x82])];
+        let decoded = Utf8Decoder::builder()
+            .error_policy(ErrorPolicy::Replace)
+            .observer(Recorder(events.clone()))
+            .build(stream::iter(chunks).into_async_read())
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(vec!["$\u{FFFD}", "\u{FFFD}"], decoded);
+        assert_eq!(
+            vec![
+                "read 2",
+                "invalid true",
+                "chunk $\u{FFFD}",
+                "read 2",
+                "eof",
+                "invalid true",
+                "chunk \u{FFFD}",
+                "eof",
+            ],
+            *events.lock().unwrap()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+//! Decoder of completion based readers which own the buffer during a read.
+use crate::decoder::{check_capacity, default_capacity, ErrorPolicy, Result};
+use crate::sans_io::Utf8DecoderCore;
+use std::future::Future;
+use std::io;
+
+/// Reader which takes the ownership of a buffer while reading into it, as the
+/// readers of completion based runtimes such as `monoio` and `tokio-uring` do.
+///
+/// Implement it for the reader of a runtime which is not supported by the
+/// crate to decode it with [`OwnedUtf8Decoder`].
+pub trait OwnedRead {
+    /// Read into `buf` from the start up to its capacity, and return the number
+    /// of bytes read along with the buffer, whose length is set to the number.
+    ///
+    /// `Ok(0)` means the reader has been closed.
+    fn read_owned(&mut self, buf: Vec<u8>) -> impl Future<Output = (io::Result<usize>, Vec<u8>)>;
+}
+
+/// Incremental UTF-8 decoder of an [`OwnedRead`] reader.
+///
+/// Unlike [`Utf8Decoder`], which lends its buffer to the reader for the duration
+/// of a poll, it hands the buffer to the reader for a whole read and takes it
+/// back once the read has completed, so readers built on io_uring can be
+/// decoded. A sequence split across reads is kept in a [`Utf8DecoderCore`]
+/// until the rest arrives, and invalid input is dealt with in the same way.
+///
+/// It is driven with [`OwnedUtf8Decoder::next`] instead of being a `Stream`.
+/// When the future is dropped before the read completes, the buffer stays with
+/// the runtime and a new one is allocated for the next read.
+///
+/// [`Utf8Decoder`]: crate::Utf8Decoder
+#[derive(Debug)]
+pub struct OwnedUtf8Decoder<R> {
+    reader: R,
+    core: Utf8DecoderCore,
+    capacity: usize,
+    // 'buf[start..]' holds bytes which have been read but not fed yet
+    buf: Vec<u8>,
+    start: usize,
+    eof: bool,
+}
+
+impl<R> OwnedUtf8Decoder<R> {
+    /// Create a new incremental UTF-8 decoder from `reader`
+    pub fn new(reader: R) -> Self {
+        OwnedUtf8Decoder::with_capacity(default_capacity(), reader)
+    }
+
+    /// Create a new incremental UTF-8 decoder from `reader` which reads at most
+    /// `capacity` bytes at once
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is smaller than 4 bytes, the maximum length of a
+    /// UTF-8 sequence.
+    pub fn with_capacity(capacity: usize, reader: R) -> Self {
+        if let Err(err) = check_capacity(capacity) {
+            panic!("{}", err);
+        }
+        Self {
+            reader,
+            core: Utf8DecoderCore::new(),
+            capacity,
+            buf: Vec::with_capacity(capacity),
+            start: 0,
+            eof: false,
+        }
+    }
+
+    /// Set the policy of invalid input. See [`ErrorPolicy`].
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.core.set_error_policy(policy);
+    }
+
+    /// Consumes this decoder, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Acquires a reference to the underlying reader that this
+    /// decoder is pulling from.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader that
+    /// this decoder is pulling from.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R> OwnedUtf8Decoder<R>
+where
+    R: OwnedRead,
+{
+    /// Decode the next chunk, or return `None` once the reader has been closed.
+    pub async fn next(&mut self) -> Option<Result<String>> {
+        loop {
+            if self.start < self.buf.len() {
+                match self.core.feed(&self.buf[self.start..]) {
+                    Ok((decoded, consumed)) => {
+                        self.start += consumed;
+                        if !decoded.is_empty() {
+                            return Some(Ok(decoded));
+                        }
+                    }
+                    Err(err) => {
+                        // The core drops the bytes an invalid sequence is found in
+                        self.start = self.buf.len();
+                        return Some(Err(err));
+                    }
+                }
+                continue;
+            }
+            if self.eof {
+                return None;
+            }
+            let mut buf = std::mem::take(&mut self.buf);
+            buf.clear();
+            if buf.capacity() == 0 {
+                // The buffer has been lost along with a cancelled read
+                buf.reserve_exact(self.capacity);
+            }
+            let (result, buf) = self.reader.read_owned(buf).await;
+            self.buf = buf;
+            self.start = 0;
+            match result {
+                // The upstream is closed
+                Ok(0) => {
+                    self.buf.clear();
+                    self.eof = true;
+                    return match self.core.finish() {
+                        Ok(decoded) if decoded.is_empty() => None,
+                        result => Some(result),
+                    };
+                }
+                Ok(n) => self.buf.truncate(n),
+                Err(err) => {
+                    self.buf.clear();
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DecodeError;
+    use anyhow::Result;
+    use std::collections::VecDeque;
+
+    struct Chunks(VecDeque<Vec<u8>>);
+
+    impl OwnedRead for Chunks {
+        async fn read_owned(&mut self, mut buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+            let chunk = self.0.pop_front().unwrap_or_default();
+            assert!(chunk.len() <= buf.capacity());
+            buf.extend_from_slice(&chunk);
+            (Ok(chunk.len()), buf)
+        }
+    }
+
+    #[async_std::test]
+    async fn owned_decoder_decode_across_reads() -> Result<()> {
+        let chunks = vec![vec![0x24, 0xF0], vec![0x9F, 0x92, 0x96, 0x24], vec![0xE2]];
+        let mut decoder = OwnedUtf8Decoder::with_capacity(4, Chunks(chunks.into()));
+
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        assert_eq!("💖$", decoder.next().await.unwrap()?);
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::IncompleteUtf8Sequence { offset: 6, .. }))
+        ));
+        assert!(decoder.next().await.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! Buffers and decoders shared across connections.
+use crate::decoder::Utf8Decoder;
+use bytes::BytesMut;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Default maximum number of idle buffers kept by a pool
+const DEFAULT_MAX_IDLE: usize = 1024;
+
+/// Pool of internal buffers which many [`Utf8Decoder`] instances draw from.
+///
+/// A decoder created by [`Utf8Decoder::with_pool`] takes an idle buffer from the
+/// pool and returns it when dropped, so servers creating many short-lived
+/// decoders do not allocate a buffer for each of them. Cloning the pool is cheap
+/// and the clones share the idle buffers.
+///
+/// [`Utf8Decoder`]: crate::Utf8Decoder
+/// [`Utf8Decoder::with_pool`]: crate::Utf8Decoder::with_pool
+#[derive(Debug, Clone)]
+pub struct BufferPool {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    buffer_size: usize,
+    max_idle: usize,
+    idle: Mutex<Vec<BytesMut>>,
+}
+
+impl BufferPool {
+    /// Create a new pool of buffers with `buffer_size` bytes of capacity
+    pub fn new(buffer_size: usize) -> Self {
+        BufferPool::with_max_idle(buffer_size, DEFAULT_MAX_IDLE)
+    }
+
+    /// Create a new pool which keeps at most `max_idle` idle buffers
+    pub fn with_max_idle(buffer_size: usize, max_idle: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                buffer_size,
+                max_idle,
+                idle: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Returns the capacity of the buffers in this pool.
+    pub fn buffer_size(&self) -> usize {
+        self.inner.buffer_size
+    }
+
+    /// Returns the number of idle buffers in this pool.
+    pub fn idle(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Take an idle buffer or allocate a new one
+    pub(crate) fn take(&self) -> BytesMut {
+        self.lock()
+            .pop()
+            .unwrap_or_else(|| BytesMut::with_capacity(self.inner.buffer_size))
+    }
+
+    /// Return a buffer to the pool unless it is too small, still shared with
+    /// split off chunks, or the pool is full
+    pub(crate) fn give(&self, mut buf: BytesMut) {
+        buf.clear();
+        if !buf.try_reclaim(self.inner.buffer_size) {
+            return;
+        }
+        let mut idle = self.lock();
+        if idle.len() < self.inner.max_idle {
+            idle.push(buf);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<BytesMut>> {
+        // The list stays consistent even if a thread panicked while holding it
+        self.inner
+            .idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Pool of whole [`Utf8Decoder`] instances keyed by their configuration.
+///
+/// Released decoders are [reset](Utf8Decoder::reset) and kept with their buffer
+/// and configuration, so accept loops reuse them instead of setting a new one
+/// up for each connection. What belongs to a connection, which is the callback
+/// of [`Utf8Decoder::on_drop_remainder`], the observer, and the warnings, is
+/// removed on release. The key identifies the configuration a decoder has
+/// been created with.
+pub struct DecoderPool<K> {
+    max_idle: usize,
+    idle: Mutex<HashMap<K, Vec<Utf8Decoder<()>>>>,
+}
+
+impl<K> fmt::Debug for DecoderPool<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let idle: usize = self.lock().values().map(|idle| idle.len()).sum();
+        f.debug_struct("DecoderPool")
+            .field("max_idle", &self.max_idle)
+            .field("idle", &idle)
+            .finish()
+    }
+}
+
+impl<K> Default for DecoderPool<K> {
+    fn default() -> Self {
+        DecoderPool::with_max_idle(DEFAULT_MAX_IDLE)
+    }
+}
+
+impl<K> DecoderPool<K> {
+    /// Create a new pool of decoders
+    pub fn new() -> Self {
+        DecoderPool::default()
+    }
+
+    /// Create a new pool which keeps at most `max_idle` idle decoders per key
+    pub fn with_max_idle(max_idle: usize) -> Self {
+        Self {
+            max_idle,
+            idle: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, Vec<Utf8Decoder<()>>>> {
+        self.idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<K> DecoderPool<K>
+where
+    K: Eq + Hash,
+{
+    /// Take an idle decoder for `key` reading from `reader`, or create a new one
+    /// with `create` when there is none.
+    pub fn acquire<R, F>(&self, key: &K, reader: R, create: F) -> Utf8Decoder<R>
+    where
+        F: FnOnce(R) -> Utf8Decoder<R>,
+    {
+        let decoder = self.lock().get_mut(key).and_then(|idle| idle.pop());
+        match decoder {
+            Some(decoder) => decoder.replace_reader(reader).0,
+            None => create(reader),
+        }
+    }
+
+    /// Return `decoder` created for `key` to the pool and give its reader back.
+    pub fn release<R>(&self, key: K, decoder: Utf8Decoder<R>) -> R {
+        let (mut decoder, reader) = decoder.replace_reader(());
+        decoder.detach();
+        let mut idle = self.lock();
+        let idle = idle.entry(key).or_default();
+        if idle.len() < self.max_idle {
+            idle.push(decoder);
+        }
+        reader
+    }
+
+    /// Returns the number of idle decoders for `key`.
+    pub fn idle(&self, key: &K) -> usize {
+        self.lock().get(key).map_or(0, |idle| idle.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderObserver, Utf8Decoder};
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[async_std::test]
+    async fn pool_recycles_buffers() -> Result<()> {
+        let pool = BufferPool::with_max_idle(64, 1);
+        let decode = |bytes: Vec<u8>| {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(bytes)];
+            Utf8Decoder::with_pool(&pool, stream::iter(chunks).into_async_read())
+                .try_collect::<String>()
+        };
+
+        assert_eq!("$", decode(vec![0x24]).await?);
+        assert_eq!(1, pool.idle());
+
+        let first = Utf8Decoder::with_pool(&pool, io::empty());
+        assert_eq!(0, pool.idle());
+        let second = Utf8Decoder::with_pool(&pool, io::empty());
+        drop(first);
+        drop(second);
+        assert_eq!(1, pool.idle());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn pool_recycles_decoders() -> Result<()> {
+        let pool = DecoderPool::new();
+        let reader = |bytes: Vec<u8>| {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(bytes)];
+            stream::iter(chunks).into_async_read()
+        };
+        let create = |reader| Utf8Decoder::with_capacity(16, reader);
+
+        let mut decoder = pool.acquire(&"small", reader(vec![0x24, 0xF0]), create);
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        let _ = pool.release("small", decoder);
+        assert_eq!(1, pool.idle(&"small"));
+        assert_eq!(0, pool.idle(&"large"));
+
+        // The pending byte of the previous reader is gone
+        let decoder = pool.acquire(&"small", reader(vec![0x24]), |_| unreachable!());
+        assert_eq!(16, decoder.capacity());
+        assert_eq!("$", decoder.try_collect::<String>().await?);
+        assert_eq!(0, pool.idle(&"small"));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn pool_detaches_released_decoders() -> Result<()> {
+        struct Counter(Arc<AtomicUsize>);
+
+        impl DecoderObserver for Counter {
+            fn on_chunk(&mut self, _chunk: &str) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let pool = DecoderPool::new();
+        let chunks = Arc::new(AtomicUsize::new(0));
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let mut decoder = pool.acquire(&(), io::Cursor::new(vec![0x24]), Utf8Decoder::new);
+        decoder.set_observer(Some(Box::new(Counter(chunks.clone()))));
+        let on_drop = {
+            let dropped = dropped.clone();
+            move |_: &[u8]| {
+                dropped.fetch_add(1, Ordering::SeqCst);
+            }
+        };
+        decoder.on_drop_remainder(on_drop);
+        let mut warnings = decoder.warnings();
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        let _ = pool.release((), decoder);
+        let timeout = std::time::Duration::from_millis(100);
+        assert!(async_std::future::timeout(timeout, warnings.next())
+            .await?
+            .is_none());
+
+        // The reused decoder reports nothing to the previous connection
+        let reader = io::Cursor::new(vec![0x24, 0xE2]);
+        let mut decoder = pool.acquire(&(), reader, |_| unreachable!());
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        drop(decoder);
+        assert_eq!(1, chunks.load(Ordering::SeqCst));
+        assert_eq!(0, dropped.load(Ordering::SeqCst));
+
+        Ok(())
+    }
+}
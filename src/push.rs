@@ -0,0 +1,173 @@
+//! Decoder which bytes are pushed to through a `Sink`.
+use crate::decoder::{ErrorPolicy, Result};
+use crate::sans_io::Utf8DecoderCore;
+use futures_core::{FusedStream, Stream};
+use futures_sink::Sink;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+const DEFAULT_QUEUE_SIZE: usize = 32; // Maximum number of items queued before the sink waits
+
+/// Incremental UTF-8 decoder which bytes are pushed to rather than pulled from
+/// a reader.
+///
+/// It is a [`Sink`] of bytes on the input side and a [`Stream`] of decoded
+/// strings on the output side, so the producer and the consumer may live in
+/// different tasks once it is split with `StreamExt::split`. The sink waits
+/// while the decoded items are not taken by the stream. Closing the sink ends
+/// the stream after the remaining items.
+///
+/// Invalid input is dealt with according to the [`ErrorPolicy`] as
+/// [`Utf8DecoderCore`] does, and errors are yielded by the stream.
+#[derive(Debug, Default)]
+pub struct PushUtf8Decoder {
+    core: Utf8DecoderCore,
+    queue: VecDeque<Result<String>>,
+    closed: bool,
+    // Tasks waiting for an item and for room in the queue
+    stream_waker: Option<Waker>,
+    sink_waker: Option<Waker>,
+}
+
+impl PushUtf8Decoder {
+    /// Create a new push decoder which reports invalid input as errors
+    pub fn new() -> Self {
+        PushUtf8Decoder::default()
+    }
+
+    /// Create a new push decoder which deals with invalid input according to `policy`
+    pub fn with_error_policy(policy: ErrorPolicy) -> Self {
+        Self {
+            core: Utf8DecoderCore::with_error_policy(policy),
+            ..PushUtf8Decoder::default()
+        }
+    }
+
+    fn push(&mut self, item: Result<String>) {
+        if matches!(&item, Ok(s) if s.is_empty()) {
+            return;
+        }
+        self.queue.push_back(item);
+        if let Some(waker) = self.stream_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<B> Sink<B> for PushUtf8Decoder
+where
+    B: AsRef<[u8]>,
+{
+    type Error = Infallible;
+
+    fn poll_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::result::Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if this.queue.len() >= DEFAULT_QUEUE_SIZE {
+            this.sink_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: B) -> std::result::Result<(), Self::Error> {
+        let this = self.get_mut();
+        let mut bytes = item.as_ref();
+        while !bytes.is_empty() {
+            match this.core.feed(bytes) {
+                Ok((decoded, consumed)) => {
+                    this.push(Ok(decoded));
+                    bytes = &bytes[consumed..];
+                }
+                Err(err) => {
+                    this.push(Err(err));
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<std::result::Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<std::result::Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if !this.closed {
+            let result = this.core.finish();
+            this.push(result);
+            this.closed = true;
+            if let Some(waker) = this.stream_waker.take() {
+                waker.wake();
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Stream for PushUtf8Decoder {
+    type Item = Result<String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(item) = this.queue.pop_front() {
+            if let Some(waker) = this.sink_waker.take() {
+                waker.wake();
+            }
+            return Poll::Ready(Some(item));
+        }
+        if this.closed {
+            return Poll::Ready(None);
+        }
+        this.stream_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl FusedStream for PushUtf8Decoder {
+    fn is_terminated(&self) -> bool {
+        self.closed && self.queue.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DecodeError;
+    use anyhow::Result;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn push_decoder_decode_pushed_bytes() -> Result<()> {
+        let (mut sink, stream) = PushUtf8Decoder::new().split();
+        let producer = async_std::task::spawn(async move {
+            for chunk in "$ü€💖".repeat(64).as_bytes().chunks(3) {
+                sink.send(chunk.to_vec()).await?;
+            }
+            sink.send(vec![0xF0]).await?;
+            sink.close().await
+        });
+
+        let mut decoded = stream.collect::<Vec<_>>().await;
+        producer.await.unwrap();
+        assert!(matches!(
+            decoded.pop(),
+            Some(Err(DecodeError::IncompleteUtf8Sequence { bytes: remains, .. })) if remains == [0xF0]
+        ));
+        let decoded = decoded.into_iter().collect::<super::Result<String>>()?;
+        assert_eq!("$ü€💖".repeat(64), decoded);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,511 @@
+//! Decoder which is fed with bytes instead of reading them.
+use crate::ascii::ascii_prefix_len;
+use crate::error::{DecodeError, ErrorKind, ErrorPolicy, Result, SequenceError};
+use crate::validated;
+use crate::validation::{sequence_len, validate};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Incremental UTF-8 decoder which does no I/O.
+///
+/// Bytes are pushed with [`Utf8DecoderCore::feed`] in chunks of any size and
+/// the text decoded so far is returned. A sequence split across chunks is kept
+/// inside the core, which holds at most 3 bytes, until the rest arrives. It
+/// allows to decode text from transports which are not an `AsyncRead`, such as
+/// QUIC streams or custom ring buffers.
+///
+/// [`Utf8DecoderCore::feed_into`] writes the text into a buffer of the caller
+/// instead, so that decoding does not allocate at all, errors included. The
+/// crate still links `alloc`, so a target without a heap needs a global
+/// allocator to build, but nothing is allocated with it.
+///
+/// Invalid input is dealt with according to the [`ErrorPolicy`] in the same way
+/// as [`Utf8Decoder`] does.
+///
+/// [`Utf8Decoder`]: crate::Utf8Decoder
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Utf8DecoderCore {
+    pending: Pending,
+    policy: ErrorPolicy,
+    // Number of bytes consumed so far, which is the offset of the next chunk
+    position: u64,
+}
+
+impl Utf8DecoderCore {
+    /// Create a new core which reports invalid input as errors
+    pub fn new() -> Self {
+        Utf8DecoderCore::default()
+    }
+
+    /// Create a new core which deals with invalid input according to `policy`
+    pub fn with_error_policy(policy: ErrorPolicy) -> Self {
+        Self {
+            policy,
+            ..Utf8DecoderCore::default()
+        }
+    }
+
+    /// Set the policy of invalid input. See [`ErrorPolicy`].
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns the bytes of an incomplete sequence kept for the next chunk.
+    pub fn pending_bytes(&self) -> &[u8] {
+        self.pending.as_bytes()
+    }
+
+    /// Decode `bytes` and return the decoded text along with the number of
+    /// bytes consumed.
+    ///
+    /// An incomplete sequence at the end is kept for the next call and counts
+    /// as consumed, so all of `bytes` are consumed. As [`Utf8Decoder`] drops
+    /// the read an invalid sequence is found in, an invalid sequence reported
+    /// with [`ErrorPolicy::Strict`] drops the bytes given, including the
+    /// sequence kept from the previous call. They are carried by the error
+    /// along with the text before the invalid sequence, see
+    /// [`DecodeError::as_valid_prefix`].
+    ///
+    /// [`Utf8Decoder`]: crate::Utf8Decoder
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(String, usize)> {
+        let mut out = Vec::new();
+        let consumed = self.feed_dropping(bytes, &mut out)?;
+        Ok((validated::into_string(out), consumed))
+    }
+
+    /// Same as [`Utf8DecoderCore::feed`] but writes the decoded text into `out`
+    /// without allocating, and returns the number of bytes written along with
+    /// the number of bytes consumed.
+    ///
+    /// Decoding also stops when `out` is full, in which case the caller feeds
+    /// the rest again after taking the text out. `out` should be at least 4
+    /// bytes long, otherwise a character may never fit.
+    ///
+    /// An error does not allocate either, so it carries only the invalid
+    /// sequence. The valid text before it is left at the start of `out`, see
+    /// [`SequenceError::valid_len`].
+    pub fn feed_into(
+        &mut self,
+        bytes: &[u8],
+        out: &mut [u8],
+    ) -> core::result::Result<(usize, usize), SequenceError> {
+        let mut out = SliceOutput { buf: out, len: 0 };
+        let consumed = self.decode(bytes, &mut out)?;
+        Ok((out.len, consumed))
+    }
+
+    /// Same as [`Utf8DecoderCore::feed_into`] but reports an error as
+    /// [`Utf8DecoderCore::feed`] does, carrying the bytes dropped along with it.
+    #[cfg(feature = "std")]
+    pub(crate) fn feed_into_dropping(
+        &mut self,
+        bytes: &[u8],
+        out: &mut [u8],
+    ) -> Result<(usize, usize)> {
+        let mut out = SliceOutput { buf: out, len: 0 };
+        let consumed = self.feed_dropping(bytes, &mut out)?;
+        Ok((out.len, consumed))
+    }
+
+    fn feed_dropping<O: Output>(&mut self, bytes: &[u8], out: &mut O) -> Result<usize> {
+        // The bytes dropped by an error start with the kept sequence
+        let pending = self.pending;
+        let base = self.position;
+        self.decode(bytes, out).map_err(|_| {
+            let dropped = [pending.as_bytes(), bytes].concat();
+            DecodeError::invalid_dropping(&dropped, base - pending.len() as u64)
+        })
+    }
+
+    fn decode<O: Output>(
+        &mut self,
+        bytes: &[u8],
+        out: &mut O,
+    ) -> core::result::Result<usize, SequenceError> {
+        let base = self.position;
+        let result = self.decode_at(base, bytes, out);
+        // The bytes given with an error are dropped
+        let consumed = result.as_ref().map_or(bytes.len(), |consumed| *consumed);
+        self.position += consumed as u64;
+        result
+    }
+
+    fn decode_at<O: Output>(
+        &mut self,
+        base: u64,
+        bytes: &[u8],
+        out: &mut O,
+    ) -> core::result::Result<usize, SequenceError> {
+        let mut consumed = 0;
+        if !self.pending.is_empty() {
+            // Either the completed character or a replacement has to fit
+            if out.room() < 4 {
+                return Ok(0);
+            }
+            // Complete the sequence left by the previous chunk first
+            match self.pending.complete(bytes) {
+                Completion::Valid(taken) => {
+                    out.write(self.pending.as_bytes());
+                    consumed = taken;
+                }
+                Completion::Incomplete => return Ok(bytes.len()),
+                Completion::Invalid(taken) => match self.policy.replacement() {
+                    Some(replacement) => {
+                        out.write(replacement);
+                        consumed = taken;
+                    }
+                    None => {
+                        let sequence = self.pending.as_bytes();
+                        let offset = base - (sequence.len() - taken) as u64;
+                        let err = SequenceError::new(ErrorKind::InvalidUtf8, sequence, offset, 0);
+                        self.pending.clear();
+                        return Err(err);
+                    }
+                },
+            }
+            self.pending.clear();
+        }
+        loop {
+            let rest = &bytes[consumed..];
+            if rest.is_empty() {
+                return Ok(consumed);
+            }
+            let chunk = &rest[..rest.len().min(out.room())];
+            let truncated = chunk.len() < rest.len();
+            match step(chunk, self.policy) {
+                Step::Valid(valid) => {
+                    out.write(&chunk[..valid]);
+                    consumed += valid;
+                    // The character may continue beyond the room of the output
+                    if truncated {
+                        return Ok(consumed);
+                    }
+                    if valid < chunk.len() {
+                        self.pending.set(&chunk[valid..]);
+                        return Ok(bytes.len());
+                    }
+                }
+                Step::Replace {
+                    valid,
+                    len,
+                    replacement,
+                } => {
+                    out.write(&chunk[..valid]);
+                    consumed += valid;
+                    if out.room() < replacement.len() {
+                        return Ok(consumed);
+                    }
+                    out.write(replacement);
+                    consumed += len;
+                }
+                Step::Fail { valid, len } => {
+                    out.write(&chunk[..valid]);
+                    let sequence = &chunk[valid..valid + len];
+                    let offset = base + (consumed + valid) as u64;
+                    let kind = ErrorKind::InvalidUtf8;
+                    return Err(SequenceError::new(kind, sequence, offset, out.len()));
+                }
+            }
+        }
+    }
+
+    /// Forget the incomplete sequence kept for the next chunk as if it has not
+    /// been fed, and return its length.
+    #[cfg(any(feature = "tokio_util", feature = "asynchronous_codec"))]
+    pub(crate) fn unkeep(&mut self) -> usize {
+        let n = self.pending.len();
+        self.pending.clear();
+        self.position -= n as u64;
+        n
+    }
+
+    /// Finish decoding at the end of the input and return the text for an
+    /// incomplete sequence kept from the last chunk, if any.
+    ///
+    /// The sequence is reported as [`DecodeError::IncompleteUtf8Sequence`] with
+    /// [`ErrorPolicy::Strict`]. The core can be fed again afterwards.
+    pub fn finish(&mut self) -> Result<String> {
+        let mut out = [0; 3];
+        let n = self.finish_into(&mut out)?;
+        Ok(validated::to_str(&out[..n]).to_string())
+    }
+
+    /// Same as [`Utf8DecoderCore::finish`] but writes the text into `out` and
+    /// returns the number of bytes written. An error does not allocate as with
+    /// [`Utf8DecoderCore::feed_into`].
+    ///
+    /// When `out` has no room for the replacement, nothing is written and the
+    /// sequence is kept, so the caller calls it again with a larger `out` while
+    /// [`Utf8DecoderCore::pending_bytes`] is not empty.
+    pub fn finish_into(&mut self, out: &mut [u8]) -> core::result::Result<usize, SequenceError> {
+        if self.pending.is_empty() {
+            return Ok(0);
+        }
+        let pending = self.pending;
+        match self.policy.replacement() {
+            Some(replacement) if out.len() < replacement.len() => Ok(0),
+            Some(replacement) => {
+                self.pending.clear();
+                out[..replacement.len()].copy_from_slice(replacement);
+                Ok(replacement.len())
+            }
+            None => {
+                self.pending.clear();
+                let offset = self.position - pending.len() as u64;
+                let kind = ErrorKind::Incomplete;
+                Err(SequenceError::new(kind, pending.as_bytes(), offset, 0))
+            }
+        }
+    }
+}
+
+/// Leading bytes of a sequence which continues in the next chunk
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) struct Pending {
+    bytes: [u8; 4],
+    len: usize,
+}
+
+/// Result of completing a [`Pending`] sequence with the next chunk
+pub(crate) enum Completion {
+    /// The sequence is a character which takes the given number of bytes of
+    /// the chunk
+    Valid(usize),
+    /// The sequence continues beyond the chunk, all of which has been taken
+    Incomplete,
+    /// The sequence is invalid, and the given number of bytes of the chunk
+    /// are part of it
+    Invalid(usize),
+}
+
+impl Pending {
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Keep `bytes`, an incomplete sequence at the end of a chunk
+    pub(crate) fn set(&mut self, bytes: &[u8]) {
+        self.bytes[..bytes.len()].copy_from_slice(bytes);
+        self.len = bytes.len();
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Complete the sequence with the continuation bytes at the start of
+    /// `chunk`. Bytes which do not continue it are left for the chunk itself.
+    ///
+    /// The completed sequence, or only its invalid bytes, is kept until
+    /// [`Pending::clear`] is called.
+    pub(crate) fn complete(&mut self, chunk: &[u8]) -> Completion {
+        let kept = self.len;
+        let need = sequence_len(self.bytes[0]) - kept;
+        let taken = chunk
+            .iter()
+            .take(need)
+            .take_while(|b| **b & 0xC0 == 0x80)
+            .count();
+        self.bytes[kept..kept + taken].copy_from_slice(&chunk[..taken]);
+        self.len += taken;
+        match core::str::from_utf8(self.as_bytes()) {
+            Ok(_) => Completion::Valid(taken),
+            Err(err) if err.error_len().is_none() && taken == chunk.len() => Completion::Incomplete,
+            Err(err) => {
+                // The kept bytes are a valid prefix, so the invalid sequence
+                // covers them
+                self.len = err.error_len().unwrap_or(self.len);
+                Completion::Invalid(self.len - kept)
+            }
+        }
+    }
+}
+
+/// How the bytes after the valid prefix of a chunk are dealt with
+pub(crate) enum Step {
+    /// The chunk is valid up to the end, or up to an incomplete sequence at
+    /// the end which starts at the given position
+    Valid(usize),
+    /// An invalid sequence of `len` bytes which follows `valid` bytes is
+    /// replaced with `replacement`
+    Replace {
+        valid: usize,
+        len: usize,
+        replacement: &'static [u8],
+    },
+    /// An invalid sequence of `len` bytes which follows `valid` bytes is
+    /// reported, and the chunk is dropped along with the error
+    Fail { valid: usize, len: usize },
+}
+
+/// Find the end of the valid prefix of `chunk` and decide how to deal with
+/// the bytes after it according to `policy`.
+///
+/// All the decoders share it so that they draw the same boundaries and deal
+/// with invalid input in the same way.
+pub(crate) fn step(chunk: &[u8], policy: ErrorPolicy) -> Step {
+    // Leading ASCII bytes are valid by definition so skip them in the validation
+    let ascii = ascii_prefix_len(chunk);
+    if ascii == chunk.len() {
+        return Step::Valid(ascii);
+    }
+    let Err(err) = validate(&chunk[ascii..]) else {
+        return Step::Valid(chunk.len());
+    };
+    let valid = ascii + err.valid_up_to;
+    match (err.error_len, policy.replacement()) {
+        (None, _) => Step::Valid(valid),
+        (Some(len), Some(replacement)) => Step::Replace {
+            valid,
+            len,
+            replacement,
+        },
+        (Some(len), None) => Step::Fail { valid, len },
+    }
+}
+
+/// Destination of the decoded text
+trait Output {
+    /// Number of bytes which have been written
+    fn len(&self) -> usize;
+    /// Number of bytes which can be written
+    fn room(&self) -> usize;
+    fn write(&mut self, bytes: &[u8]);
+}
+
+impl Output for Vec<u8> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn room(&self) -> usize {
+        usize::MAX
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+struct SliceOutput<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Output for SliceOutput<'_> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn room(&self) -> usize {
+        self.buf.len() - self.len
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_decode_across_chunks() {
+        let text = "$ü€💖".repeat(4);
+        for size in 1..8 {
+            let mut core = Utf8DecoderCore::new();
+            let mut decoded = String::new();
+            for chunk in text.as_bytes().chunks(size) {
+                let (s, consumed) = core.feed(chunk).unwrap();
+                assert_eq!(chunk.len(), consumed);
+                decoded.push_str(&s);
+            }
+            decoded.push_str(&core.finish().unwrap());
+            assert_eq!(text, decoded);
+        }
+    }
+
+    #[test]
+    fn core_decode_invalid() {
+        let mut core = Utf8DecoderCore::new();
+        let bytes = [0x24, 0xFF, 0x24];
+        let err = core.feed(&bytes).unwrap_err();
+        assert!(matches!(err, DecodeError::Utf8Error { offset: 1, .. }));
+        assert_eq!(Some("$"), err.as_valid_prefix());
+        assert_eq!(("".to_string(), 2), core.feed(&[0xF0, 0x9F]).unwrap());
+        assert!(matches!(
+            core.finish(),
+            Err(DecodeError::IncompleteUtf8Sequence { bytes: remains, offset: 3, .. }) if remains == [0xF0, 0x9F]
+        ));
+        // The kept sequence is dropped along with the chunk
+        assert_eq!(("$".to_string(), 2), core.feed(&[0x24, 0xE2]).unwrap());
+        let err = core.feed(&[0x82, 0xAC, 0xFF]).unwrap_err();
+        assert!(matches!(err, DecodeError::Utf8Error { offset: 9, .. }));
+        assert_eq!(Some("€"), err.as_valid_prefix());
+
+        let mut core = Utf8DecoderCore::with_error_policy(ErrorPolicy::Replace);
+        assert_eq!(("$\u{FFFD}$".to_string(), 3), core.feed(&bytes).unwrap());
+        // A sequence interrupted by another character
+        assert_eq!(("".to_string(), 1), core.feed(&[0xE2]).unwrap());
+        assert_eq!(("\u{FFFD}$".to_string(), 1), core.feed(&[0x24]).unwrap());
+        assert_eq!(("".to_string(), 1), core.feed(&[0xE2]).unwrap());
+        assert_eq!("\u{FFFD}", core.finish().unwrap());
+    }
+
+    #[test]
+    fn core_decode_into_slice() {
+        let text = "$ü€💖".repeat(4);
+        let mut core = Utf8DecoderCore::with_error_policy(ErrorPolicy::Replace);
+        let mut out = [0; 6];
+        let mut decoded = String::new();
+        let mut bytes = text.as_bytes();
+        while !bytes.is_empty() {
+            let (written, consumed) = core.feed_into(bytes, &mut out).unwrap();
+            decoded.push_str(core::str::from_utf8(&out[..written]).unwrap());
+            bytes = &bytes[consumed..];
+        }
+        let written = core.finish_into(&mut out).unwrap();
+        assert_eq!(0, written);
+        assert_eq!(text, decoded);
+
+        // The replacement waits for room in the output
+        assert_eq!((4, 4), core.feed_into(&[0x24; 4], &mut out).unwrap());
+        assert_eq!((0, 0), core.feed_into(&[0xFF], &mut out[4..]).unwrap());
+        assert_eq!((3, 1), core.feed_into(&[0xFF], &mut out[3..]).unwrap());
+
+        // The sequence is kept until the replacement fits
+        assert_eq!((0, 1), core.feed_into(&[0xE2], &mut out).unwrap());
+        assert_eq!(0, core.finish_into(&mut out[..2]).unwrap());
+        assert_eq!(&[0xE2], core.pending_bytes());
+        assert_eq!(3, core.finish_into(&mut out).unwrap());
+        assert_eq!("\u{FFFD}".as_bytes(), &out[..3]);
+        assert!(core.pending_bytes().is_empty());
+
+        // Errors carry the sequence without allocating
+        let mut core = Utf8DecoderCore::new();
+        let err = core.feed_into(&[0x24, 0xFF, 0x24], &mut out).unwrap_err();
+        assert_eq!(ErrorKind::InvalidUtf8, err.kind());
+        assert_eq!((1, &[0xFF][..]), (err.offset(), err.as_bytes()));
+        assert_eq!(b"$", &out[..err.valid_len()]);
+        assert_eq!((0, 2), core.feed_into(&[0xF0, 0x9F], &mut out).unwrap());
+        let err = core.finish_into(&mut out).unwrap_err();
+        assert_eq!(ErrorKind::Incomplete, err.kind());
+        assert_eq!((3, &[0xF0, 0x9F][..]), (err.offset(), err.as_bytes()));
+        assert!(matches!(
+            DecodeError::from(err),
+            DecodeError::IncompleteUtf8Sequence { offset: 3, .. }
+        ));
+    }
+}
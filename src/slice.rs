@@ -0,0 +1,154 @@
+//! Zero-copy decoding of in-memory bytes.
+use crate::ascii::ascii_prefix_len;
+use crate::decoder::Result;
+use crate::error::DecodeError;
+use crate::validated;
+use crate::validation::validate;
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+const MINIMUM_CHUNK_SIZE: usize = 4; // Maximum utf-8 character byte length
+
+/// Decoder of in-memory bytes which yields string slices of the source itself.
+///
+/// It yields the same items as [`Utf8Decoder`] would yield from an in-memory
+/// reader such as `&[u8]` or a cursor, but validates and slices the source
+/// directly instead of staging it through an internal buffer. It is both an
+/// [`Iterator`] and a [`Stream`] which is always ready.
+///
+/// An invalid sequence is reported after the valid text before it has been
+/// yielded, and only the invalid bytes are skipped.
+///
+/// [`Utf8Decoder`]: crate::Utf8Decoder
+#[derive(Debug, Clone)]
+pub struct Utf8Slices<'a> {
+    bytes: &'a [u8],
+    chunk_size: usize,
+    // Offset of 'bytes' in the source
+    offset: u64,
+}
+
+impl<'a> Utf8Slices<'a> {
+    /// Create a new decoder of `bytes`
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Utf8Slices::with_capacity(DEFAULT_CHUNK_SIZE, bytes)
+    }
+
+    /// Create a new decoder of `bytes` which yields at most `capacity` bytes at once
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is smaller than 4 bytes, the maximum length of a
+    /// UTF-8 sequence.
+    pub fn with_capacity(capacity: usize, bytes: &'a [u8]) -> Self {
+        assert!(
+            capacity >= MINIMUM_CHUNK_SIZE,
+            "capacity must be at least {} but {} is specified",
+            MINIMUM_CHUNK_SIZE,
+            capacity,
+        );
+        Self {
+            bytes,
+            chunk_size: capacity,
+            offset: 0,
+        }
+    }
+
+    /// Returns the bytes which have not been decoded yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl<'a> Iterator for Utf8Slices<'a> {
+    type Item = Result<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let bytes = self.bytes;
+        let chunk = &bytes[..bytes.len().min(self.chunk_size)];
+        let ascii = ascii_prefix_len(chunk);
+        let valid = match validate(&chunk[ascii..]) {
+            Ok(_) => chunk.len(),
+            Err(err) => {
+                let valid = ascii + err.valid_up_to;
+                match err.error_len {
+                    Some(_) if valid > 0 => valid,
+                    Some(n) => {
+                        let err = DecodeError::invalid_at(chunk, self.offset);
+                        self.bytes = &bytes[n..];
+                        self.offset += n as u64;
+                        return Some(Err(err));
+                    }
+                    // The sequence continues in the next chunk
+                    None if valid > 0 => valid,
+                    None => {
+                        self.bytes = &[];
+                        let remains = chunk.to_vec();
+                        let err = DecodeError::incomplete_at(remains, self.offset);
+                        self.offset += chunk.len() as u64;
+                        return Some(Err(err));
+                    }
+                }
+            }
+        };
+        let (decoded, rest) = bytes.split_at(valid);
+        self.bytes = rest;
+        self.offset += valid as u64;
+        Some(Ok(validated::to_str(decoded)))
+    }
+}
+
+impl<'a> Stream for Utf8Slices<'a> {
+    type Item = Result<&'a str>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        Poll::Ready(self.get_mut().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use futures::TryStreamExt;
+
+    #[async_std::test]
+    async fn slices_decode_in_chunks() -> Result<()> {
+        let text = "$ü€💖".repeat(4);
+        let slices = Utf8Slices::with_capacity(5, text.as_bytes());
+        let decoded = slices.try_collect::<Vec<_>>().await?;
+
+        assert_eq!(text, decoded.concat());
+        assert!(decoded.iter().all(|s| s.len() <= 5));
+        // Slices point into the source
+        assert_eq!(text.as_ptr(), decoded[0].as_ptr());
+
+        Ok(())
+    }
+
+    #[test]
+    fn slices_decode_invalid() {
+        let bytes = [0x24, 0xFF, 0x24, 0xF0, 0x9F];
+        let mut slices = Utf8Slices::new(&bytes);
+
+        assert_eq!("$", slices.next().unwrap().unwrap());
+        assert!(matches!(
+            slices.next(),
+            Some(Err(DecodeError::Utf8Error { offset: 1, .. }))
+        ));
+        assert_eq!("$", slices.next().unwrap().unwrap());
+        assert!(matches!(
+            slices.next(),
+            Some(Err(DecodeError::IncompleteUtf8Sequence { bytes: remains, offset: 3, .. })) if remains == [0xF0, 0x9F]
+        ));
+        assert!(slices.next().is_none());
+    }
+}
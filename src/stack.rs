@@ -0,0 +1,184 @@
+//! Decoder whose buffers live inline instead of on the heap.
+use crate::decoder::{ErrorPolicy, Result};
+use crate::sans_io::Utf8DecoderCore;
+use crate::validated;
+use futures_core::{ready, Stream};
+use futures_io::AsyncRead;
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Incremental UTF-8 decoder whose buffers are `N` byte arrays stored inline.
+///
+/// Unlike [`Utf8Decoder`], creating and driving it does not touch the heap
+/// when the text is taken with [`StackUtf8Decoder::next_str`]. It holds two
+/// buffers of `N` bytes, one for the bytes read and another for the decoded
+/// text, which should be considered when it is placed on a small stack.
+/// Each chunk is at most `N` bytes long.
+///
+/// It deals with invalid input in the same way as [`Utf8DecoderCore`].
+///
+/// The reader must be [`Unpin`]. Pin a reader which is not with
+/// [`std::pin::pin!`] and pass the pinned reference instead.
+///
+/// [`Utf8Decoder`]: crate::Utf8Decoder
+#[derive(Debug)]
+pub struct StackUtf8Decoder<R, const N: usize> {
+    reader: R,
+    core: Utf8DecoderCore,
+    // 'input[start..end]' holds bytes which have been read but not fed yet
+    input: [u8; N],
+    start: usize,
+    end: usize,
+    output: [u8; N],
+    eof: bool,
+}
+
+impl<R, const N: usize> StackUtf8Decoder<R, N> {
+    // Evaluated on use so that a too small N fails to compile
+    const CHECK_SIZE: () = assert!(N >= 4, "N must be at least 4");
+
+    /// Create a new incremental UTF-8 decoder from `reader`
+    pub fn new(reader: R) -> Self {
+        StackUtf8Decoder::with_error_policy(ErrorPolicy::Strict, reader)
+    }
+
+    /// Create a new incremental UTF-8 decoder from `reader` which deals with
+    /// invalid input according to `policy`
+    pub fn with_error_policy(policy: ErrorPolicy, reader: R) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK_SIZE;
+        Self {
+            reader,
+            core: Utf8DecoderCore::with_error_policy(policy),
+            input: [0; N],
+            start: 0,
+            end: 0,
+            output: [0; N],
+            eof: false,
+        }
+    }
+
+    /// Consumes this decoder, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Acquires a reference to the underlying reader that this
+    /// decoder is pulling from.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader that
+    /// this decoder is pulling from.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R, const N: usize> StackUtf8Decoder<R, N>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Attempt to decode the next chunk into the output buffer and return its
+    /// length.
+    fn poll_decode(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<usize>>> {
+        loop {
+            if self.start < self.end {
+                let bytes = &self.input[self.start..self.end];
+                match self.core.feed_into_dropping(bytes, &mut self.output) {
+                    Ok((written, consumed)) => {
+                        self.start += consumed;
+                        if written > 0 {
+                            return Poll::Ready(Some(Ok(written)));
+                        }
+                    }
+                    Err(err) => {
+                        // The core drops the bytes an invalid sequence is found in
+                        self.start = self.end;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                continue;
+            }
+            if self.eof {
+                return Poll::Ready(None);
+            }
+            let n = ready!(Pin::new(&mut self.reader).poll_read(cx, &mut self.input))?;
+            self.start = 0;
+            self.end = n.min(N);
+            // The upstream is closed
+            if n == 0 {
+                self.eof = true;
+                return match self.core.finish_into(&mut self.output) {
+                    Ok(0) => Poll::Ready(None),
+                    result => Poll::Ready(Some(result.map_err(Into::into))),
+                };
+            }
+        }
+    }
+
+    /// Decode the next chunk and return it as a borrow into the output buffer.
+    ///
+    /// The returned string slice is valid until the next call on this decoder.
+    pub async fn next_str(&mut self) -> Option<Result<&str>> {
+        let result = poll_fn(|cx| self.poll_decode(cx)).await;
+        match result? {
+            Ok(written) => Some(Ok(validated::to_str(&self.output[..written]))),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl<R, const N: usize> Stream for StackUtf8Decoder<R, N>
+where
+    R: AsyncRead + Unpin,
+{
+    type Item = Result<String>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let this = self.get_mut();
+        let result = ready!(this.poll_decode(cx));
+        let output = &this.output;
+        Poll::Ready(result.map(|r| r.map(|n| validated::to_str(&output[..n]).to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DecodeError;
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn stack_decoder_decode_in_chunks() -> Result<()> {
+        let text = "$ü€💖".repeat(4);
+        let mut decoder = StackUtf8Decoder::<_, 5>::new(io::Cursor::new(text.clone()));
+        let mut decoded = String::new();
+        while let Some(chunk) = decoder.next_str().await {
+            let chunk = chunk?;
+            assert!(chunk.len() <= 5);
+            decoded.push_str(chunk);
+        }
+        assert_eq!(text, decoded);
+
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x24, 0xFF]), Ok(vec![0xF0, 0x9F])];
+        let mut decoder = StackUtf8Decoder::<_, 16>::new(stream::iter(chunks).into_async_read());
+        let err = decoder.next().await.unwrap().unwrap_err();
+        assert!(matches!(err, DecodeError::Utf8Error { offset: 1, .. }));
+        assert_eq!(Some("$"), err.as_valid_prefix());
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::IncompleteUtf8Sequence { bytes: remains, .. })) if remains == [0xF0, 0x9F]
+        ));
+        assert!(decoder.next().await.is_none());
+
+        Ok(())
+    }
+}
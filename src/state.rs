@@ -0,0 +1,63 @@
+//! Decoder state which can be re-attached to a new reader.
+use crate::decoder::{Autotune, ErrorPolicy, ReadStrategy};
+
+/// State of a [`Utf8Decoder`] apart from its reader.
+///
+/// Export it with [`Utf8Decoder::state`] and re-attach it to a new reader with
+/// [`Utf8Decoder::from_state`]. With the `serde` feature it can be serialized,
+/// which allows to migrate a session to another process.
+///
+/// [`Utf8Decoder`]: crate::Utf8Decoder
+/// [`Utf8Decoder::state`]: crate::Utf8Decoder::state
+/// [`Utf8Decoder::from_state`]: crate::Utf8Decoder::from_state
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecoderState {
+    /// Bytes which have been read but not decoded yet
+    pub remainder: Vec<u8>,
+    /// Capacity of the internal buffer
+    pub capacity: usize,
+    /// Maximum number of bytes the internal buffer has held
+    pub high_water_mark: usize,
+    /// See [`Utf8Decoder::set_read_strategy`](crate::Utf8Decoder::set_read_strategy)
+    pub read_strategy: ReadStrategy,
+    /// See [`Utf8Decoder::set_error_policy`](crate::Utf8Decoder::set_error_policy)
+    pub error_policy: ErrorPolicy,
+    /// See [`Utf8Decoder::set_autotune`](crate::Utf8Decoder::set_autotune)
+    pub autotune: Option<Autotune>,
+    /// See [`Utf8Decoder::set_direct_read`](crate::Utf8Decoder::set_direct_read)
+    pub direct_read: bool,
+    /// See [`Utf8Decoder::set_shrink_to_fit`](crate::Utf8Decoder::set_shrink_to_fit)
+    pub shrink_to_fit: bool,
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::channel::mpsc;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn state_roundtrip() -> Result<()> {
+        let (mut tx, rx) = mpsc::unbounded::<io::Result<Vec<u8>>>();
+        let mut decoder = Utf8Decoder::with_capacity(16, rx.into_async_read());
+        decoder.set_error_policy(ErrorPolicy::Replace);
+
+        tx.send(Ok(vec![0x24, 0xF0, 0x9F])).await?;
+        assert_eq!("$", decoder.next().await.unwrap()?);
+        let json = serde_json::to_string(&decoder.state())?;
+        drop(decoder);
+
+        let state: DecoderState = serde_json::from_str(&json)?;
+        assert_eq!(vec![0xF0, 0x9F], state.remainder);
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0x92, 0x96, 0xFF])];
+        let decoder = Utf8Decoder::from_state(state, stream::iter(chunks).into_async_read());
+        assert_eq!(16, decoder.capacity());
+        assert_eq!("💖\u{FFFD}", decoder.try_collect::<String>().await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,24 @@
+//! Cumulative statistics of a decoder.
+
+/// Cumulative statistics of a [`Utf8Decoder`] returned by [`Utf8Decoder::stats`].
+///
+/// Counters start at zero and are cleared by [`Utf8Decoder::reset`].
+///
+/// [`Utf8Decoder`]: crate::Utf8Decoder
+/// [`Utf8Decoder::stats`]: crate::Utf8Decoder::stats
+/// [`Utf8Decoder::reset`]: crate::Utf8Decoder::reset
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecoderStats {
+    /// Number of bytes read from the reader
+    pub bytes_read: u64,
+    /// Number of reads which returned some bytes
+    pub reads: u64,
+    /// Number of items yielded
+    pub items: u64,
+    /// Number of invalid sequences encountered, including an incomplete
+    /// sequence at the end of the input
+    pub invalid_sequences: u64,
+    /// Number of invalid sequences replaced with U+FFFD
+    pub replacements: u64,
+}
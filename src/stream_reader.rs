@@ -0,0 +1,143 @@
+//! Reader which reads out of a stream of byte chunks.
+use futures_core::{ready, Stream};
+use futures_io::AsyncRead;
+use pin_project_lite::pin_project;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Reader which reads the chunks yielded by a stream of `Result<B, E>`.
+    ///
+    /// It is created by [`Utf8Decoder::from_stream`] so that a stream such as
+    /// the receiver of a channel or the body of a response is decoded without
+    /// `into_async_read` of `futures-util`. Chunks are anything which is
+    /// `AsRef<[u8]>`, such as `Vec<u8>`, `Bytes`, or `&'static [u8]`, and
+    /// errors are anything which converts into `io::Error`.
+    ///
+    /// [`Utf8Decoder::from_stream`]: crate::Utf8Decoder::from_stream
+    #[derive(Debug)]
+    pub struct StreamReader<S, B = Vec<u8>> {
+        #[pin]
+        stream: S,
+        // Chunk which has not been read entirely
+        chunk: Option<B>,
+        pos: usize,
+    }
+}
+
+impl<S, B> StreamReader<S, B> {
+    /// Create a new reader which reads the chunks of `stream`
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            chunk: None,
+            pos: 0,
+        }
+    }
+
+    /// Consumes this reader, returning the underlying stream.
+    ///
+    /// The rest of a chunk which has been read partially is lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Acquires a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+impl<S, B, E> AsyncRead for StreamReader<S, B>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: Into<io::Error>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        // Empty chunks are skipped as they would be taken for the end
+        while this
+            .chunk
+            .as_ref()
+            .is_none_or(|c| *this.pos == c.as_ref().len())
+        {
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(chunk) => {
+                    *this.chunk = Some(chunk.map_err(Into::into)?);
+                    *this.pos = 0;
+                }
+                None => {
+                    *this.chunk = None;
+                    return Poll::Ready(Ok(0));
+                }
+            }
+        }
+        let rest = this
+            .chunk
+            .as_ref()
+            .map_or(&[][..], |c| &c.as_ref()[*this.pos..]);
+        let n = rest.len().min(buf.len());
+        buf[..n].copy_from_slice(&rest[..n]);
+        *this.pos += n;
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DecodeError, Utf8Decoder};
+    use anyhow::Result;
+    use bytes::Bytes;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn stream_reader_decode() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![
+            Ok(vec![0x24, 0xF0]),
+            Ok(vec![]),
+            Ok(vec![0x9F, 0x92, 0x96, 0x24]),
+        ];
+        let decoded = Utf8Decoder::from_stream(stream::iter(chunks)).try_collect::<String>();
+        assert_eq!("$💖$", decoded.await?);
+
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Err(io::ErrorKind::BrokenPipe.into())];
+        let mut decoder = Utf8Decoder::from_stream(stream::iter(chunks));
+        assert!(matches!(
+            decoder.next().await,
+            Some(Err(DecodeError::IOError(_)))
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn stream_reader_generic_chunks() -> Result<()> {
+        let chunks: Vec<io::Result<Bytes>> = vec![
+            Ok(Bytes::from_static(&[0x24, 0xF0])),
+            Ok(Bytes::from_static(&[0x9F, 0x92, 0x96])),
+        ];
+        let decoded = Utf8Decoder::from_stream(stream::iter(chunks)).try_collect::<String>();
+        assert_eq!("$💖", decoded.await?);
+
+        let chunks = stream::iter(["$ü", "€💖"].map(|s| Ok::<_, io::ErrorKind>(s.as_bytes())));
+        let decoded = Utf8Decoder::from_stream(chunks).try_collect::<String>();
+        assert_eq!("$ü€💖", decoded.await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+//! Reader which stops after a number of bytes.
+use futures_core::ready;
+use futures_io::AsyncRead;
+use pin_project_lite::pin_project;
+use std::convert::TryFrom;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pin_project! {
+    /// Reader which reads at most `limit` bytes from `reader`.
+    ///
+    /// It is created by [`Utf8Decoder::take_bytes`]. It never reads beyond the
+    /// limit, so the rest of the underlying reader is left for the next consumer
+    /// once this reader is taken back with [`TakeReader::into_inner`].
+    ///
+    /// [`Utf8Decoder::take_bytes`]: crate::Utf8Decoder::take_bytes
+    #[derive(Debug)]
+    pub struct TakeReader<R> {
+        #[pin]
+        reader: R,
+        limit: u64,
+    }
+}
+
+impl<R> TakeReader<R> {
+    /// Create a new reader which reads at most `limit` bytes from `reader`
+    pub fn new(reader: R, limit: u64) -> Self {
+        Self { reader, limit }
+    }
+
+    /// Returns the number of bytes which can still be read.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Consumes this reader, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Acquires a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R> AsyncRead for TakeReader<R>
+where
+    R: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        if *this.limit == 0 {
+            return Poll::Ready(Ok(0));
+        }
+        let max = buf
+            .len()
+            .min(usize::try_from(*this.limit).unwrap_or(usize::MAX));
+        let n = ready!(this.reader.poll_read(cx, &mut buf[..max]))?;
+        *this.limit -= n as u64;
+        Poll::Ready(Ok(n))
+    }
+}
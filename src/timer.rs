@@ -0,0 +1,100 @@
+//! Timeouts of a decoder which are enabled by the `timer` feature.
+use crate::error::DecodeError;
+use std::task::{Context, Poll};
+#[cfg(feature = "timer")]
+use {
+    futures_core::ready, futures_timer::Delay, std::future::Future, std::pin::Pin,
+    std::time::Duration,
+};
+
+/// Deadline which is armed on the first poll and restarted on demand
+#[cfg(feature = "timer")]
+#[derive(Debug)]
+pub(crate) struct Deadline {
+    duration: Duration,
+    delay: Option<Delay>,
+}
+
+#[cfg(feature = "timer")]
+impl Deadline {
+    pub(crate) fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            delay: None,
+        }
+    }
+
+    /// Disarm the deadline so that the next poll starts it over
+    pub(crate) fn restart(&mut self) {
+        self.delay = None;
+    }
+
+    /// Poll the deadline, which restarts once it has expired
+    pub(crate) fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let duration = self.duration;
+        let delay = self.delay.get_or_insert_with(|| Delay::new(duration));
+        ready!(Pin::new(delay).poll(cx));
+        self.delay = None;
+        Poll::Ready(())
+    }
+}
+
+/// Timer which has expired while the reader is pending
+#[cfg_attr(not(feature = "timer"), allow(dead_code))]
+pub(crate) enum Expired {
+    /// The reader has not returned any bytes within the read timeout
+    Read(DecodeError),
+    /// An incomplete sequence has not been completed within its timeout
+    Incomplete,
+}
+
+/// Timers of a decoder, which are empty unless the `timer` feature is enabled
+#[derive(Debug, Default)]
+pub(crate) struct Timers {
+    #[cfg(feature = "timer")]
+    pub(crate) read: Option<Deadline>,
+    #[cfg(feature = "timer")]
+    pub(crate) incomplete: Option<Deadline>,
+}
+
+impl Timers {
+    /// Poll the timers while the reader is pending. `progressed` tells whether
+    /// some bytes have been read in the meantime, and `incomplete` whether an
+    /// incomplete sequence is waiting for the rest.
+    pub(crate) fn poll_pending(
+        &mut self,
+        cx: &mut Context<'_>,
+        progressed: bool,
+        incomplete: bool,
+    ) -> Poll<Expired> {
+        #[cfg(feature = "timer")]
+        {
+            if let Some(deadline) = &mut self.incomplete {
+                if progressed || !incomplete {
+                    deadline.restart();
+                }
+                if incomplete && deadline.poll_expired(cx).is_ready() {
+                    return Poll::Ready(Expired::Incomplete);
+                }
+            }
+            if let Some(read) = &mut self.read {
+                if progressed {
+                    read.restart();
+                }
+                ready!(read.poll_expired(cx));
+                return Poll::Ready(Expired::Read(DecodeError::Timeout(read.duration)));
+            }
+        }
+        let _ = (cx, progressed, incomplete);
+        Poll::Pending
+    }
+
+    /// Called when the decoder yields something
+    pub(crate) fn on_ready(&mut self) {
+        #[cfg(feature = "timer")]
+        {
+            self.read.iter_mut().for_each(Deadline::restart);
+            self.incomplete.iter_mut().for_each(Deadline::restart);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Reader which adapts a `tokio::io::AsyncRead`.
+use futures_core::ready;
+use futures_io::AsyncRead;
+use pin_project_lite::pin_project;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::ReadBuf;
+
+pin_project! {
+    /// Reader which reads from a [`tokio::io::AsyncRead`] with a [`ReadBuf`].
+    ///
+    /// It is created by [`Utf8Decoder::from_tokio`] so that tokio readers are
+    /// decoded without the compat layer of `tokio-util`.
+    ///
+    /// [`Utf8Decoder::from_tokio`]: crate::Utf8Decoder::from_tokio
+    #[derive(Debug)]
+    pub struct TokioReader<R> {
+        #[pin]
+        reader: R,
+    }
+}
+
+impl<R> TokioReader<R> {
+    /// Create a new reader which reads from `reader`
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Consumes this reader, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Acquires a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl<R> AsyncRead for TokioReader<R>
+where
+    R: tokio::io::AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = ReadBuf::new(buf);
+        ready!(self.project().reader.poll_read(cx, &mut buf))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Utf8Decoder;
+    use anyhow::Result;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn tokio_reader_decode() -> Result<()> {
+        let text = "$ü€💖".repeat(4);
+        // '&[u8]' implements both traits, so take the tokio one explicitly
+        let reader: Box<dyn tokio::io::AsyncRead + Unpin> = Box::new(text.as_bytes());
+        let decoded = Utf8Decoder::from_tokio(reader).try_collect::<String>();
+        assert_eq!(text, decoded.await?);
+
+        Ok(())
+    }
+}
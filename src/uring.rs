@@ -0,0 +1,98 @@
+//! Reader which adapts the files and streams of `tokio-uring`.
+use crate::owned::{OwnedRead, OwnedUtf8Decoder};
+use std::io;
+use tokio_uring::fs::File;
+use tokio_uring::net::{TcpStream, UnixStream};
+
+/// Reader which reads from a `tokio-uring` [`File`], [`TcpStream`], or
+/// [`UnixStream`] for [`OwnedUtf8Decoder`].
+///
+/// It is created by [`OwnedUtf8Decoder::from_uring`]. A file is read from the
+/// start, and the position of the next read is kept in the reader as files of
+/// `tokio-uring` are read at explicit positions.
+#[derive(Debug)]
+pub struct UringReader<R> {
+    reader: R,
+    pos: u64,
+}
+
+impl<R> UringReader<R> {
+    /// Create a new reader which reads from `reader`
+    pub fn new(reader: R) -> Self {
+        Self { reader, pos: 0 }
+    }
+
+    /// Consumes this reader, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Acquires a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Acquires a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+}
+
+impl OwnedRead for UringReader<File> {
+    async fn read_owned(&mut self, buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        let (result, buf) = self.reader.read_at(buf, self.pos).await;
+        if let Ok(n) = result {
+            self.pos += n as u64;
+        }
+        (result, buf)
+    }
+}
+
+impl OwnedRead for UringReader<TcpStream> {
+    async fn read_owned(&mut self, buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        self.reader.read(buf).await
+    }
+}
+
+impl OwnedRead for UringReader<UnixStream> {
+    async fn read_owned(&mut self, buf: Vec<u8>) -> (io::Result<usize>, Vec<u8>) {
+        self.reader.read(buf).await
+    }
+}
+
+impl<R> OwnedUtf8Decoder<UringReader<R>>
+where
+    UringReader<R>: OwnedRead,
+{
+    /// Create a new incremental UTF-8 decoder from a `tokio-uring` `reader`.
+    /// See [`UringReader`].
+    pub fn from_uring(reader: R) -> Self {
+        OwnedUtf8Decoder::new(UringReader::new(reader))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn uring_reader_decode_file() -> Result<()> {
+        let text = "$ü€💖".repeat(4);
+        let path = std::env::temp_dir().join("async-utf8-decoder-uring.txt");
+        std::fs::write(&path, &text)?;
+        let decoded = tokio_uring::start(async {
+            let file = File::open(&path).await?;
+            let mut decoder = OwnedUtf8Decoder::with_capacity(5, UringReader::new(file));
+            let mut decoded = String::new();
+            while let Some(chunk) = decoder.next().await {
+                decoded.push_str(&chunk?);
+            }
+            Ok::<_, anyhow::Error>(decoded)
+        });
+        std::fs::remove_file(&path)?;
+        assert_eq!(text, decoded?);
+
+        Ok(())
+    }
+}
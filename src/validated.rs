@@ -0,0 +1,33 @@
+//! Conversions of bytes which have already been validated as UTF-8.
+//!
+//! These skip the second validation with `unsafe` code when the `unchecked`
+//! feature is enabled and validate again otherwise, so that the crate can be
+//! built with `#![forbid(unsafe_code)]`.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Convert validated `bytes` into `&str`
+#[cfg(feature = "unchecked")]
+pub(crate) fn to_str(bytes: &[u8]) -> &str {
+    // SAFETY: The caller guarantees that 'bytes' is valid UTF-8
+    unsafe { core::str::from_utf8_unchecked(bytes) }
+}
+
+/// Convert validated `bytes` into `&str`
+#[cfg(not(feature = "unchecked"))]
+pub(crate) fn to_str(bytes: &[u8]) -> &str {
+    core::str::from_utf8(bytes).expect("bytes must have been validated")
+}
+
+/// Convert validated `bytes` into `String` without copying
+#[cfg(feature = "unchecked")]
+pub(crate) fn into_string(bytes: Vec<u8>) -> String {
+    // SAFETY: The caller guarantees that 'bytes' is valid UTF-8
+    unsafe { String::from_utf8_unchecked(bytes) }
+}
+
+/// Convert validated `bytes` into `String` without copying
+#[cfg(not(feature = "unchecked"))]
+pub(crate) fn into_string(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).expect("bytes must have been validated")
+}
@@ -0,0 +1,174 @@
+#[cfg(feature = "std")]
+use core::ops::Range;
+
+/// Position of the first invalid or incomplete sequence, equivalent to the
+/// accessors of [`core::str::Utf8Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Invalid {
+    pub(crate) valid_up_to: usize,
+    pub(crate) error_len: Option<usize>,
+}
+
+impl From<core::str::Utf8Error> for Invalid {
+    fn from(err: core::str::Utf8Error) -> Self {
+        Self {
+            valid_up_to: err.valid_up_to(),
+            error_len: err.error_len(),
+        }
+    }
+}
+
+/// Validate `bytes` as UTF-8 with the same result as [`core::str::from_utf8`].
+#[cfg(feature = "rayon")]
+pub(crate) fn validate(bytes: &[u8]) -> Result<(), Invalid> {
+    if bytes.len() >= PARALLEL_THRESHOLD {
+        parallel_validate(bytes)
+    } else {
+        sequential_validate(bytes)
+    }
+}
+
+/// Validate `bytes` as UTF-8 with the same result as [`core::str::from_utf8`].
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn validate(bytes: &[u8]) -> Result<(), Invalid> {
+    sequential_validate(bytes)
+}
+
+/// Append `bytes` to `out` with the invalid sequences replaced by `replacement`
+/// and return the number of bytes consumed, which excludes an incomplete
+/// sequence at the end, along with the positions of the invalid sequences.
+#[cfg(feature = "std")]
+pub(crate) fn replace_invalid(
+    mut bytes: &[u8],
+    replacement: &[u8],
+    out: &mut Vec<u8>,
+) -> (usize, Vec<Range<usize>>) {
+    let len = bytes.len();
+    let mut invalid_sequences = Vec::new();
+    loop {
+        match core::str::from_utf8(bytes) {
+            Ok(_) => {
+                out.extend_from_slice(bytes);
+                return (len, invalid_sequences);
+            }
+            Err(err) => {
+                let (valid, invalid) = bytes.split_at(err.valid_up_to());
+                out.extend_from_slice(valid);
+                match err.error_len() {
+                    Some(n) => {
+                        out.extend_from_slice(replacement);
+                        let start = len - invalid.len();
+                        invalid_sequences.push(start..start + n);
+                        bytes = &invalid[n..];
+                    }
+                    None => return (len - invalid.len(), invalid_sequences),
+                }
+            }
+        }
+    }
+}
+
+/// Length of the multi-byte sequence which starts with `lead`
+pub(crate) fn sequence_len(lead: u8) -> usize {
+    match lead {
+        0xF0..=0xFF => 4,
+        0xE0..=0xEF => 3,
+        _ => 2,
+    }
+}
+
+fn sequential_validate(bytes: &[u8]) -> Result<(), Invalid> {
+    core::str::from_utf8(bytes)
+        .map(|_| ())
+        .map_err(Invalid::from)
+}
+
+/// Minimum length of bytes validated in parallel
+#[cfg(feature = "rayon")]
+const PARALLEL_THRESHOLD: usize = 256 * 1024;
+/// Length of each segment validated in parallel
+#[cfg(feature = "rayon")]
+const SEGMENT_SIZE: usize = 64 * 1024;
+
+#[cfg(feature = "rayon")]
+fn parallel_validate(bytes: &[u8]) -> Result<(), Invalid> {
+    use rayon::prelude::*;
+
+    // Split at the start of a character so that each segment of valid input is
+    // valid on its own. Continuation bytes are skipped backwards up to the
+    // maximum length of a sequence, and a split which still lands on one is an
+    // error which the segment after it reports.
+    let mut starts = vec![0];
+    let mut start = SEGMENT_SIZE;
+    while start < bytes.len() {
+        let mut split = start;
+        while split > start - 3 && is_continuation(bytes[split]) {
+            split -= 1;
+        }
+        starts.push(split);
+        start += SEGMENT_SIZE;
+    }
+    starts.push(bytes.len());
+
+    let invalid = starts
+        .par_windows(2)
+        .position_first(|w| core::str::from_utf8(&bytes[w[0]..w[1]]).is_err());
+    match invalid {
+        None => Ok(()),
+        // Every segment before is valid and ends at a character boundary, so
+        // the rest can be validated on its own to locate the error.
+        Some(i) => {
+            let offset = starts[i];
+            let err = sequential_validate(&bytes[offset..]).unwrap_err();
+            Err(Invalid {
+                valid_up_to: offset + err.valid_up_to,
+                ..err
+            })
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+#[cfg(all(test, feature = "rayon"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_validate_matches_from_utf8() {
+        let text = "$ü€💖".repeat(PARALLEL_THRESHOLD / 10 * 2);
+        let bytes = text.as_bytes();
+        assert_eq!(Ok(()), parallel_validate(bytes));
+
+        // Position of the first character starting at or after `i`
+        fn lead(b: &[u8], i: usize, non_ascii: bool) -> usize {
+            (i..)
+                .find(|&i| !is_continuation(b[i]) && (!non_ascii || !b[i].is_ascii()))
+                .unwrap()
+        }
+        let cases: Vec<fn(&mut Vec<u8>)> = vec![
+            // Incomplete tail
+            |b| b.truncate(b.len() - 1),
+            // Invalid bytes around segment boundaries
+            |b| b[SEGMENT_SIZE] = 0xFF,
+            |b| {
+                let i = lead(b, SEGMENT_SIZE - 2, false);
+                b[i] = 0x80;
+            },
+            |b| {
+                let i = lead(b, SEGMENT_SIZE * 2 - 1, true);
+                b[i] = 0x24;
+            },
+            |b| b[SEGMENT_SIZE * 3..SEGMENT_SIZE * 3 + 8].fill(0x80),
+        ];
+        for case in cases {
+            let mut bytes = bytes.to_vec();
+            case(&mut bytes);
+            assert_eq!(sequential_validate(&bytes), parallel_validate(&bytes));
+            assert!(parallel_validate(&bytes).is_err());
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Side channel of the invalid input which has been replaced or skipped.
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Invalid sequence which has been replaced with U+FFFD or skipped instead of
+/// being reported as an error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeWarning {
+    /// Offset of the sequence in the input. See [`DecodeError::offset`].
+    ///
+    /// [`DecodeError::offset`]: crate::DecodeError::offset
+    pub offset: u64,
+    /// Bytes of the sequence
+    pub bytes: Vec<u8>,
+    /// Whether it has been replaced with U+FFFD rather than skipped
+    pub replaced: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    queue: VecDeque<DecodeWarning>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// Stream of [`DecodeWarning`] created by [`Utf8Decoder::warnings`].
+///
+/// Warnings are queued without a bound until they are taken, and the stream
+/// ends once the decoder is dropped or another stream has been created.
+///
+/// [`Utf8Decoder::warnings`]: crate::Utf8Decoder::warnings
+#[derive(Debug)]
+pub struct Warnings {
+    shared: Arc<Mutex<Shared>>,
+}
+
+/// Sending half of [`Warnings`] held by the buffer of a decoder
+pub(crate) struct WarningSender {
+    shared: Arc<Mutex<Shared>>,
+}
+
+pub(crate) fn channel() -> (WarningSender, Warnings) {
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    let sender = WarningSender {
+        shared: shared.clone(),
+    };
+    (sender, Warnings { shared })
+}
+
+impl WarningSender {
+    pub(crate) fn send(&self, warning: DecodeWarning) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.queue.push_back(warning);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for WarningSender {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.closed = true;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl fmt::Debug for WarningSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WarningSender")
+    }
+}
+
+impl Stream for Warnings {
+    type Item = DecodeWarning;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(warning) = shared.queue.pop_front() {
+            return Poll::Ready(Some(warning));
+        }
+        if shared.closed {
+            return Poll::Ready(None);
+        }
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorPolicy, Utf8Decoder};
+    use anyhow::Result;
+    use futures::io;
+    use futures::prelude::*;
+
+    #[async_std::test]
+    async fn warnings_report_replaced_sequences() -> Result<()> {
+        for direct in [false, true] {
+            let chunks: Vec<io::Result<Vec<u8>>> = vec![
+                Ok(vec![0x24, 0xFF, 0x24]),
+                Ok(vec![0xE2, 0x82, 0x24]),
+                Ok(vec![0xF0, 0x9F]),
+            ];
+            let mut decoder = Utf8Decoder::new(stream::iter(chunks).into_async_read());
+            decoder.set_error_policy(ErrorPolicy::Replace);
+            decoder.set_direct_read(direct);
+            let warnings = decoder.warnings();
+
+            let decoded = decoder.try_collect::<String>().await?;
+            assert_eq!("$\u{FFFD}$\u{FFFD}$\u{FFFD}", decoded);
+            let warnings = warnings.collect::<Vec<_>>().await;
+            let warning = |offset, bytes: &[u8]| DecodeWarning {
+                offset,
+                bytes: bytes.to_vec(),
+                replaced: true,
+            };
+            assert_eq!(
+                vec![
+                    warning(1, &[0xFF]),
+                    warning(3, &[0xE2, 0x82]),
+                    warning(6, &[0xF0, 0x9F]),
+                ],
+                warnings
+            );
+        }
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn warnings_offsets_across_eager_reads() -> Result<()> {
+        let chunks: Vec<io::Result<Vec<u8>>> = vec![Ok(vec![0xFF, 0x24]), Ok(vec![0xFF])];
+        let mut decoder = Utf8Decoder::builder()
+            .error_policy(ErrorPolicy::Skip)
+            .eager_drain(true)
+            .build(stream::iter(chunks).into_async_read());
+        let warnings = decoder.warnings();
+
+        assert_eq!("$", decoder.try_collect::<String>().await?);
+        let offsets = warnings.map(|w| (w.offset, w.replaced)).collect::<Vec<_>>();
+        assert_eq!(vec![(0, false), (2, false)], offsets.await);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+//! Stream of the chunks of a JavaScript `ReadableStream`.
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use wasm_streams::readable::{sys, IntoStream, ReadableStream};
+
+pin_project! {
+    /// Stream of the byte chunks of a JavaScript `ReadableStream`, such as the
+    /// body of a response of `fetch`.
+    ///
+    /// It is created by [`Utf8Decoder::from_readable_stream`] along with a
+    /// [`StreamReader`]. Chunks are expected to be `Uint8Array`, and an error
+    /// of the stream is reported as an `io::Error` of `ErrorKind::Other`.
+    ///
+    /// [`Utf8Decoder::from_readable_stream`]: crate::Utf8Decoder::from_readable_stream
+    /// [`StreamReader`]: crate::StreamReader
+    #[derive(Debug)]
+    pub struct JsChunks {
+        #[pin]
+        stream: IntoStream<'static>,
+    }
+}
+
+impl JsChunks {
+    /// Create a new stream of the chunks of `stream`
+    pub fn new(stream: sys::ReadableStream) -> Self {
+        Self {
+            stream: ReadableStream::from_raw(stream).into_stream(),
+        }
+    }
+}
+
+impl Stream for JsChunks {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let chunk = ready!(self.project().stream.poll_next(cx));
+        Poll::Ready(chunk.map(|chunk| match chunk {
+            Ok(chunk) => Ok(js_sys::Uint8Array::new(&chunk).to_vec()),
+            Err(err) => {
+                let msg = err.as_string().unwrap_or_else(|| format!("{:?}", err));
+                Err(io::Error::other(msg))
+            }
+        }))
+    }
+}
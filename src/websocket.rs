@@ -0,0 +1,169 @@
+//! Text of the messages received over a WebSocket.
+use crate::decoder::Result;
+use crate::error::ErrorPolicy;
+use crate::sans_io::Utf8DecoderCore;
+use bytes::{Buf, Bytes};
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tungstenite::Message;
+
+pin_project! {
+    /// Stream of the text carried by the messages of a WebSocket, such as a
+    /// `WebSocketStream` of `async-tungstenite` or `tokio-tungstenite`.
+    ///
+    /// Binary messages are decoded as incremental UTF-8, so a sequence split
+    /// across messages is kept until the rest arrives. Text messages are
+    /// yielded as they are, and control messages are skipped.
+    ///
+    /// Invalid input is dealt with according to the [`ErrorPolicy`]. With
+    /// [`ErrorPolicy::Strict`], the stream ends after reporting invalid input
+    /// as RFC 6455 section 8.1 requires the connection to be failed, and an
+    /// incomplete sequence at the end is reported as well.
+    #[derive(Debug)]
+    pub struct WebSocketText<S> {
+        #[pin]
+        stream: S,
+        core: Utf8DecoderCore,
+        // Bytes of the current message which have not been fed to the core yet
+        rest: Bytes,
+        done: bool,
+    }
+}
+
+impl<S> WebSocketText<S> {
+    /// Create a new stream of the text of `stream` which reports invalid input
+    /// as errors
+    pub fn new(stream: S) -> Self {
+        WebSocketText::with_error_policy(ErrorPolicy::Strict, stream)
+    }
+
+    /// Create a new stream of the text of `stream` which deals with invalid
+    /// input according to `policy`
+    pub fn with_error_policy(policy: ErrorPolicy, stream: S) -> Self {
+        Self {
+            stream,
+            core: Utf8DecoderCore::with_error_policy(policy),
+            rest: Bytes::new(),
+            done: false,
+        }
+    }
+
+    /// Consumes this stream, returning the underlying stream of messages.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Acquires a reference to the underlying stream of messages.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream of messages.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+impl<S> Stream for WebSocketText<S>
+where
+    S: Stream<Item = std::result::Result<Message, tungstenite::Error>>,
+{
+    type Item = Result<String>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<<Self as Stream>::Item>> {
+        let mut this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            if this.rest.is_empty() {
+                *this.rest = match ready!(this.stream.as_mut().poll_next(cx)) {
+                    Some(Ok(Message::Binary(data))) => data,
+                    Some(Ok(Message::Text(text))) => text.into(),
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => return Poll::Ready(Some(Err(into_io_error(err).into()))),
+                    None => {
+                        *this.done = true;
+                        return Poll::Ready(match this.core.finish() {
+                            Ok(decoded) if decoded.is_empty() => None,
+                            result => Some(result),
+                        });
+                    }
+                };
+            }
+            match this.core.feed(this.rest) {
+                Ok((decoded, consumed)) => {
+                    this.rest.advance(consumed);
+                    if !decoded.is_empty() {
+                        return Poll::Ready(Some(Ok(decoded)));
+                    }
+                }
+                Err(err) => {
+                    *this.rest = Bytes::new();
+                    *this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+fn into_io_error(err: tungstenite::Error) -> io::Error {
+    match err {
+        tungstenite::Error::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecodeError;
+    use anyhow::Result;
+    use futures::prelude::*;
+
+    fn messages(
+        messages: Vec<Message>,
+    ) -> impl Stream<Item = std::result::Result<Message, tungstenite::Error>> {
+        stream::iter(messages.into_iter().map(Ok))
+    }
+
+    #[async_std::test]
+    async fn websocket_decode_across_messages() -> Result<()> {
+        let stream = messages(vec![
+            Message::binary(vec![0x24, 0xF0]),
+            Message::Ping(Bytes::new()),
+            Message::binary(vec![0x9F, 0x92, 0x96]),
+            Message::text("ü€"),
+        ]);
+        let decoded = WebSocketText::new(stream).try_collect::<Vec<_>>().await?;
+        assert_eq!(vec!["$", "💖", "ü€"], decoded);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn websocket_fail_on_invalid() -> Result<()> {
+        let stream = messages(vec![
+            Message::binary(vec![0x24, 0xFF, 0x24]),
+            Message::binary(vec![0x24]),
+        ]);
+        let mut text = WebSocketText::new(stream);
+        let err = text.next().await.unwrap().unwrap_err();
+        assert!(matches!(err, DecodeError::Utf8Error { offset: 1, .. }));
+        assert_eq!(Some("$"), err.as_valid_prefix());
+        assert!(text.next().await.is_none());
+
+        let stream = messages(vec![Message::binary(vec![0x24, 0xFF, 0xF0])]);
+        let text = WebSocketText::with_error_policy(ErrorPolicy::Replace, stream);
+        assert_eq!("$\u{FFFD}\u{FFFD}", text.try_collect::<String>().await?);
+
+        Ok(())
+    }
+}